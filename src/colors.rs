@@ -0,0 +1,154 @@
+use image::Rgba;
+
+/// CSS named colors (CSS Color Module Level 4), keyed by lowercase name.
+pub(crate) static NAMED_COLORS: &[(&str, Rgba<u8>)] = &[
+    ("aliceblue", Rgba([240, 248, 255, 255])),
+    ("antiquewhite", Rgba([250, 235, 215, 255])),
+    ("aqua", Rgba([0, 255, 255, 255])),
+    ("aquamarine", Rgba([127, 255, 212, 255])),
+    ("azure", Rgba([240, 255, 255, 255])),
+    ("beige", Rgba([245, 245, 220, 255])),
+    ("bisque", Rgba([255, 228, 196, 255])),
+    ("black", Rgba([0, 0, 0, 255])),
+    ("blanchedalmond", Rgba([255, 235, 205, 255])),
+    ("blue", Rgba([0, 0, 255, 255])),
+    ("blueviolet", Rgba([138, 43, 226, 255])),
+    ("brown", Rgba([165, 42, 42, 255])),
+    ("burlywood", Rgba([222, 184, 135, 255])),
+    ("cadetblue", Rgba([95, 158, 160, 255])),
+    ("chartreuse", Rgba([127, 255, 0, 255])),
+    ("chocolate", Rgba([210, 105, 30, 255])),
+    ("coral", Rgba([255, 127, 80, 255])),
+    ("cornflowerblue", Rgba([100, 149, 237, 255])),
+    ("cornsilk", Rgba([255, 248, 220, 255])),
+    ("crimson", Rgba([220, 20, 60, 255])),
+    ("cyan", Rgba([0, 255, 255, 255])),
+    ("darkblue", Rgba([0, 0, 139, 255])),
+    ("darkcyan", Rgba([0, 139, 139, 255])),
+    ("darkgoldenrod", Rgba([184, 134, 11, 255])),
+    ("darkgray", Rgba([169, 169, 169, 255])),
+    ("darkgreen", Rgba([0, 100, 0, 255])),
+    ("darkgrey", Rgba([169, 169, 169, 255])),
+    ("darkkhaki", Rgba([189, 183, 107, 255])),
+    ("darkmagenta", Rgba([139, 0, 139, 255])),
+    ("darkolivegreen", Rgba([85, 107, 47, 255])),
+    ("darkorange", Rgba([255, 140, 0, 255])),
+    ("darkorchid", Rgba([153, 50, 204, 255])),
+    ("darkred", Rgba([139, 0, 0, 255])),
+    ("darksalmon", Rgba([233, 150, 122, 255])),
+    ("darkseagreen", Rgba([143, 188, 143, 255])),
+    ("darkslateblue", Rgba([72, 61, 139, 255])),
+    ("darkslategray", Rgba([47, 79, 79, 255])),
+    ("darkslategrey", Rgba([47, 79, 79, 255])),
+    ("darkturquoise", Rgba([0, 206, 209, 255])),
+    ("darkviolet", Rgba([148, 0, 211, 255])),
+    ("deeppink", Rgba([255, 20, 147, 255])),
+    ("deepskyblue", Rgba([0, 191, 255, 255])),
+    ("dimgray", Rgba([105, 105, 105, 255])),
+    ("dimgrey", Rgba([105, 105, 105, 255])),
+    ("dodgerblue", Rgba([30, 144, 255, 255])),
+    ("firebrick", Rgba([178, 34, 34, 255])),
+    ("floralwhite", Rgba([255, 250, 240, 255])),
+    ("forestgreen", Rgba([34, 139, 34, 255])),
+    ("fuchsia", Rgba([255, 0, 255, 255])),
+    ("gainsboro", Rgba([220, 220, 220, 255])),
+    ("ghostwhite", Rgba([248, 248, 255, 255])),
+    ("gold", Rgba([255, 215, 0, 255])),
+    ("goldenrod", Rgba([218, 165, 32, 255])),
+    ("gray", Rgba([128, 128, 128, 255])),
+    ("green", Rgba([0, 128, 0, 255])),
+    ("greenyellow", Rgba([173, 255, 47, 255])),
+    ("grey", Rgba([128, 128, 128, 255])),
+    ("honeydew", Rgba([240, 255, 240, 255])),
+    ("hotpink", Rgba([255, 105, 180, 255])),
+    ("indianred", Rgba([205, 92, 92, 255])),
+    ("indigo", Rgba([75, 0, 130, 255])),
+    ("ivory", Rgba([255, 255, 240, 255])),
+    ("khaki", Rgba([240, 230, 140, 255])),
+    ("lavender", Rgba([230, 230, 250, 255])),
+    ("lavenderblush", Rgba([255, 240, 245, 255])),
+    ("lawngreen", Rgba([124, 252, 0, 255])),
+    ("lemonchiffon", Rgba([255, 250, 205, 255])),
+    ("lightblue", Rgba([173, 216, 230, 255])),
+    ("lightcoral", Rgba([240, 128, 128, 255])),
+    ("lightcyan", Rgba([224, 255, 255, 255])),
+    ("lightgoldenrodyellow", Rgba([250, 250, 210, 255])),
+    ("lightgray", Rgba([211, 211, 211, 255])),
+    ("lightgreen", Rgba([144, 238, 144, 255])),
+    ("lightgrey", Rgba([211, 211, 211, 255])),
+    ("lightpink", Rgba([255, 182, 193, 255])),
+    ("lightsalmon", Rgba([255, 160, 122, 255])),
+    ("lightseagreen", Rgba([32, 178, 170, 255])),
+    ("lightskyblue", Rgba([135, 206, 250, 255])),
+    ("lightslategray", Rgba([119, 136, 153, 255])),
+    ("lightslategrey", Rgba([119, 136, 153, 255])),
+    ("lightsteelblue", Rgba([176, 196, 222, 255])),
+    ("lightyellow", Rgba([255, 255, 224, 255])),
+    ("lime", Rgba([0, 255, 0, 255])),
+    ("limegreen", Rgba([50, 205, 50, 255])),
+    ("linen", Rgba([250, 240, 230, 255])),
+    ("magenta", Rgba([255, 0, 255, 255])),
+    ("maroon", Rgba([128, 0, 0, 255])),
+    ("mediumaquamarine", Rgba([102, 205, 170, 255])),
+    ("mediumblue", Rgba([0, 0, 205, 255])),
+    ("mediumorchid", Rgba([186, 85, 211, 255])),
+    ("mediumpurple", Rgba([147, 112, 219, 255])),
+    ("mediumseagreen", Rgba([60, 179, 113, 255])),
+    ("mediumslateblue", Rgba([123, 104, 238, 255])),
+    ("mediumspringgreen", Rgba([0, 250, 154, 255])),
+    ("mediumturquoise", Rgba([72, 209, 204, 255])),
+    ("mediumvioletred", Rgba([199, 21, 133, 255])),
+    ("midnightblue", Rgba([25, 25, 112, 255])),
+    ("mintcream", Rgba([245, 255, 250, 255])),
+    ("mistyrose", Rgba([255, 228, 225, 255])),
+    ("moccasin", Rgba([255, 228, 181, 255])),
+    ("navajowhite", Rgba([255, 222, 173, 255])),
+    ("navy", Rgba([0, 0, 128, 255])),
+    ("oldlace", Rgba([253, 245, 230, 255])),
+    ("olive", Rgba([128, 128, 0, 255])),
+    ("olivedrab", Rgba([107, 142, 35, 255])),
+    ("orange", Rgba([255, 165, 0, 255])),
+    ("orangered", Rgba([255, 69, 0, 255])),
+    ("orchid", Rgba([218, 112, 214, 255])),
+    ("palegoldenrod", Rgba([238, 232, 170, 255])),
+    ("palegreen", Rgba([152, 251, 152, 255])),
+    ("paleturquoise", Rgba([175, 238, 238, 255])),
+    ("palevioletred", Rgba([219, 112, 147, 255])),
+    ("papayawhip", Rgba([255, 239, 213, 255])),
+    ("peachpuff", Rgba([255, 218, 185, 255])),
+    ("peru", Rgba([205, 133, 63, 255])),
+    ("pink", Rgba([255, 192, 203, 255])),
+    ("plum", Rgba([221, 160, 221, 255])),
+    ("powderblue", Rgba([176, 224, 230, 255])),
+    ("purple", Rgba([128, 0, 128, 255])),
+    ("rebeccapurple", Rgba([102, 51, 153, 255])),
+    ("red", Rgba([255, 0, 0, 255])),
+    ("rosybrown", Rgba([188, 143, 143, 255])),
+    ("royalblue", Rgba([65, 105, 225, 255])),
+    ("saddlebrown", Rgba([139, 69, 19, 255])),
+    ("salmon", Rgba([250, 128, 114, 255])),
+    ("sandybrown", Rgba([244, 164, 96, 255])),
+    ("seagreen", Rgba([46, 139, 87, 255])),
+    ("seashell", Rgba([255, 245, 238, 255])),
+    ("sienna", Rgba([160, 82, 45, 255])),
+    ("silver", Rgba([192, 192, 192, 255])),
+    ("skyblue", Rgba([135, 206, 235, 255])),
+    ("slateblue", Rgba([106, 90, 205, 255])),
+    ("slategray", Rgba([112, 128, 144, 255])),
+    ("slategrey", Rgba([112, 128, 144, 255])),
+    ("snow", Rgba([255, 250, 250, 255])),
+    ("springgreen", Rgba([0, 255, 127, 255])),
+    ("steelblue", Rgba([70, 130, 180, 255])),
+    ("tan", Rgba([210, 180, 140, 255])),
+    ("teal", Rgba([0, 128, 128, 255])),
+    ("thistle", Rgba([216, 191, 216, 255])),
+    ("tomato", Rgba([255, 99, 71, 255])),
+    ("transparent", Rgba([0, 0, 0, 0])),
+    ("turquoise", Rgba([64, 224, 208, 255])),
+    ("violet", Rgba([238, 130, 238, 255])),
+    ("wheat", Rgba([245, 222, 179, 255])),
+    ("white", Rgba([255, 255, 255, 255])),
+    ("whitesmoke", Rgba([245, 245, 245, 255])),
+    ("yellow", Rgba([255, 255, 0, 255])),
+    ("yellowgreen", Rgba([154, 205, 50, 255])),
+];
@@ -71,6 +71,11 @@ pub fn get_config_path() -> PathBuf {
     kv_project_dirs().config_dir.join("plugins.toml")
 }
 
+/// Path of the TOML file with defaults for the command line flags.
+pub fn get_defaults_path() -> PathBuf {
+    kv_project_dirs().config_dir.join("config.toml")
+}
+
 pub fn load_plugins() -> HashMap<String, Plugin> {
     let config_path = get_config_path();
 
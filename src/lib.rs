@@ -1,14 +1,22 @@
 use anyhow::{Context, Result};
-use image::{DynamicImage, Rgba};
+use image::codecs::gif::GifDecoder;
+use image::codecs::png::PngDecoder;
+use image::codecs::webp::WebPDecoder;
+use image::imageops::FilterType;
+use image::{AnimationDecoder, DynamicImage, ImageFormat, Rgba};
 use std::fs::File;
-use std::io::Read;
+use std::io::{Cursor, Read};
 use std::path::{Path, PathBuf};
 use std::sync::OnceLock;
-use serde;
+
+mod colors;
+use colors::NAMED_COLORS;
 
 mod config;
 pub use config::*;
 
+mod diacritics;
+
 mod render;
 pub use render::*;
 
@@ -18,16 +26,38 @@ pub use send::*;
 #[cfg(test)]
 mod tests_lib;
 
+/// Extensions of documents converted with LibreOffice, including OpenDocument formats.
+const OFFICE_EXTENSIONS: [&str; 9] = ["doc", "docx", "xls", "xlsx", "ppt", "pptx", "odt", "ods", "odp"];
+
+const PNG_MAGIC: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// Extensions of videos previewed with ffmpeg.
+#[cfg(feature = "video")]
+const VIDEO_EXTENSIONS: [&str; 6] = ["mp4", "m4v", "mov", "mkv", "webm", "avi"];
+
 static PLUGINS: OnceLock<std::collections::HashMap<String, Plugin>> = OnceLock::new();
 
 #[derive(Debug)]
 pub enum LoadResult {
     Image(DynamicImage),
+    Animation(Vec<AnimationFrame>),
+    /// A PNG image that needed no changes, with its original bytes.
+    Png { image: DynamicImage, data: Vec<u8> },
+    /// Separately rendered PDF pages (with --separate-pages).
+    Pages(Vec<DynamicImage>),
     Data(Vec<u8>),
 }
 
+/// A single rendered frame of an animation.
+#[derive(Debug, Clone)]
+pub struct AnimationFrame {
+    pub image: DynamicImage,
+    /// Time to show this frame, in milliseconds.
+    pub delay_ms: u32,
+}
+
 /// Defines how the image should be resized relative to the terminal or explicit dimensions.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum ResizeMode {
     /// -n: Use the original image dimensions, even if they are larger than the terminal.
     Original,
@@ -38,9 +68,25 @@ pub enum ResizeMode {
     /// -F: Force height to match terminal height, scaling width to preserve aspect ratio.
     FitHeight,
     /// -w / -H: Use one explicit dimension, scale the other to preserve aspect ratio.
-    Manual { width: Option<u32>, height: Option<u32> },
+    /// With both dimensions, `fit` decides how the image fills the box.
+    Manual { width: Option<u32>, height: Option<u32>, fit: Fit },
     /// Use the original size but clip the image to the terminal size.
     ClipTerminal,
+    /// --thumbnail: Scale down so the longest side is at most this many pixels, ignoring the terminal.
+    Thumbnail(u32),
+}
+
+/// How an image fills a box given by both --width and --height.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Fit {
+    /// Fit inside the box, preserving aspect ratio.
+    Contain,
+    /// Fill the box, preserving aspect ratio and cropping the overflow.
+    Cover,
+    /// Resize to exactly the box, distorting the image if needed.
+    Stretch,
+    /// Fit inside the box, preserving aspect ratio, and pad the rest with transparency.
+    Letterbox,
 }
 
 /// Configuration for file caching (used for Office/PDF conversions).
@@ -54,6 +100,40 @@ pub enum CacheMode {
     Custom(PathBuf),
 }
 
+/// Defines what is drawn behind (semi-)transparent images.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Background {
+    /// -b / -c: Fill with a solid color.
+    Color(Rgba<u8>),
+    /// --checkerboard: Draw a gray checkerboard pattern with the given cell size in pixels.
+    Checkerboard(u32),
+    /// --gradient: Draw a linear gradient, top to bottom (or left to right if not vertical).
+    Gradient {
+        from: Rgba<u8>,
+        to: Rgba<u8>,
+        vertical: bool,
+    },
+}
+
+/// Defines how multiple PDF pages are arranged in the rendered image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PageLayout {
+    /// Stack pages top to bottom.
+    Vertical,
+    /// Place pages left to right.
+    Horizontal,
+    /// Arrange pages in rows with the given number of columns.
+    Grid(u32),
+}
+
+/// Image format of HTML screenshots, lossy formats with an optional quality (0-100).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptureFormat {
+    Png,
+    Jpeg(Option<u32>),
+    Webp(Option<u32>),
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum InputType {
@@ -70,15 +150,196 @@ pub enum InputType {
 pub struct KvContext {
     pub input_type: InputType,
     pub resize_mode: ResizeMode,
+    /// Filter used when resizing images.
+    pub resize_filter: FilterType,
+    /// Caps on the calculated size, applied after the resize mode, preserving aspect ratio.
+    pub max_width: Option<u32>,
+    pub max_height: Option<u32>,
+    /// Region (x, y, width, height) cut from the source image before orienting and resizing.
+    pub crop: Option<(u32, u32, u32, u32)>,
+    /// Clockwise rotation in degrees (0, 90, 180 or 270), applied before flipping.
+    pub rotate: u32,
+    pub flip_h: bool,
+    pub flip_v: bool,
+    /// Adjustments applied after background compositing, before the color filters:
+    /// brightness offset (-255 to 255), contrast in percent and gamma (1.0 is unchanged).
+    pub brightness: i32,
+    pub contrast: f32,
+    pub gamma: f32,
+    /// Color filters applied after background compositing (grayscale, then sepia, then invert).
+    pub grayscale: bool,
+    pub sepia: bool,
+    pub invert: bool,
     /// The detected terminal size (width, height).
     pub term_size: (u32, u32),
-    pub page_indices: Option<Vec<u16>>,
+    /// Selected PDF pages, `None` for all pages.
+    pub pages: Option<Vec<PageRange>>,
+    /// Render the selected pages in the given order, duplicates included, instead of sorted.
+    pub keep_page_order: bool,
+    /// Maximum number of pages a selection may expand to.
+    pub max_pages: u32,
+    /// Render PDF pages at this resolution instead of scaling them to the target width.
+    pub pdf_dpi: Option<f32>,
+    /// Scale factor applied to the intrinsic SVG size before resizing.
+    pub svg_scale: f32,
+    /// Color used for `currentColor` in SVGs.
+    pub svg_color: Option<Rgba<u8>>,
+    /// CSS injected into SVGs before rendering.
+    pub svg_stylesheet: Option<String>,
+    /// Additional font directories and files for SVG text.
+    pub font_dirs: Vec<PathBuf>,
+    pub font_files: Vec<PathBuf>,
+    /// Do not load system fonts for SVG text.
+    pub no_system_fonts: bool,
+    pub page_layout: PageLayout,
+    /// Browser viewport (width, height) for HTML rendering, defaults to the terminal size.
+    pub viewport: Option<(u32, u32)>,
+    /// CSS selector to wait for before capturing HTML, defaults to `body`.
+    pub html_wait: Option<String>,
+    /// Extra time to wait after loading HTML, in milliseconds.
+    pub html_delay: Option<u64>,
+    pub html_capture_format: CaptureFormat,
+    /// Device pixel ratio used for HTML screenshots.
+    pub html_scale: f32,
+    /// Chrome executable for HTML rendering, auto-discovered if not set.
+    pub chrome_path: Option<PathBuf>,
+    /// Extra command line arguments for Chrome.
+    pub chrome_args: Vec<String>,
+    /// Run Chrome without its sandbox (needed in some containers).
+    pub no_sandbox: bool,
+    /// Space between combined PDF pages, in pixels.
+    pub page_gap: u32,
+    pub page_gap_color: Rgba<u8>,
+    /// Render each PDF page as its own image instead of combining them.
+    pub separate_pages: bool,
     pub cache_mode: CacheMode,
-    pub background_color: Option<Rgba<u8>>,
+    /// Cache rendered SVG and PDF images in the cache directory.
+    pub render_cache: bool,
+    /// Maximum size of the render cache in bytes.
+    pub cache_size: u64,
+    /// Offset of the previewed video frame, in seconds.
+    pub video_time: f32,
+    /// Report slow steps, like PDF pages and launching Chrome, on stderr.
+    pub progress: bool,
+    pub background: Option<Background>,
+}
+
+impl KvContext {
+    /// Creates a context with the CLI defaults for the detected terminal size.
+    /// Fields can be overridden directly or with the `with_*` methods.
+    pub fn new() -> Self {
+        KvContext {
+            input_type: InputType::Auto,
+            resize_mode: ResizeMode::ClipTerminal,
+            resize_filter: FilterType::Triangle,
+            max_width: None,
+            max_height: None,
+            crop: None,
+            rotate: 0,
+            flip_h: false,
+            flip_v: false,
+            brightness: 0,
+            contrast: 0.0,
+            gamma: 1.0,
+            grayscale: false,
+            sepia: false,
+            invert: false,
+            term_size: get_term_size(DEFAULT_RESERVE_ROWS),
+            pages: Some(vec![PageRange::from(0)]),
+            keep_page_order: false,
+            max_pages: DEFAULT_MAX_PAGES,
+            pdf_dpi: None,
+            svg_scale: 1.0,
+            svg_color: None,
+            svg_stylesheet: None,
+            font_dirs: vec![],
+            font_files: vec![],
+            no_system_fonts: false,
+            page_layout: PageLayout::Vertical,
+            viewport: None,
+            html_wait: None,
+            html_delay: None,
+            html_capture_format: CaptureFormat::Png,
+            html_scale: 1.0,
+            chrome_path: None,
+            chrome_args: vec![],
+            no_sandbox: false,
+            page_gap: 10,
+            page_gap_color: Rgba([128, 128, 128, 255]),
+            separate_pages: false,
+            cache_mode: CacheMode::Default,
+            render_cache: false,
+            cache_size: 100 * 1024 * 1024,
+            video_time: 1.0,
+            progress: false,
+            background: None,
+        }
+    }
+
+    pub fn with_input_type(mut self, input_type: InputType) -> Self {
+        self.input_type = input_type;
+        self
+    }
+
+    pub fn with_resize_mode(mut self, resize_mode: ResizeMode) -> Self {
+        self.resize_mode = resize_mode;
+        self
+    }
+
+    pub fn with_term_size(mut self, width: u32, height: u32) -> Self {
+        self.term_size = (width, height);
+        self
+    }
+
+    /// Selects PDF pages, `None` for all pages.
+    pub fn with_pages(mut self, pages: Option<Vec<PageRange>>) -> Self {
+        self.pages = pages;
+        self
+    }
+
+    pub fn with_cache_mode(mut self, cache_mode: CacheMode) -> Self {
+        self.cache_mode = cache_mode;
+        self
+    }
+
+    pub fn with_background(mut self, background: Option<Background>) -> Self {
+        self.background = background;
+        self
+    }
+
+    /// Whether rendering changes pixels besides resizing.
+    fn changes_pixels(&self) -> bool {
+        self.background.is_some()
+            || self.crop.is_some()
+            || self.rotate != 0
+            || self.flip_h
+            || self.flip_v
+            || self.brightness != 0
+            || self.contrast != 0.0
+            || self.gamma != 1.0
+            || self.grayscale
+            || self.sepia
+            || self.invert
+    }
+}
+
+impl Default for KvContext {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-/// Detects terminal size with fallbacks.
-pub fn get_term_size() -> (u32, u32) {
+/// Maximum number of pages a --pages selection may expand to by default.
+pub const DEFAULT_MAX_PAGES: u32 = 1000;
+
+/// Bytes of stdin read for format detection before deciding whether to buffer the rest.
+pub const STDIN_PREFIX_SIZE: usize = 64 * 1024;
+
+/// Terminal rows kept free below images for the prompt by default.
+pub const DEFAULT_RESERVE_ROWS: u32 = 2;
+
+/// Detects terminal size with fallbacks, leaving `reserve_rows` rows free for the prompt.
+pub fn get_term_size(reserve_rows: u32) -> (u32, u32) {
     let fallback = (800, 400);
 
     match crossterm::terminal::window_size() {
@@ -86,38 +347,227 @@ pub fn get_term_size() -> (u32, u32) {
             let cols = size.columns as u32;
             let rows = size.rows as u32;
 
-            let width = if size.width > 0 {
-                size.width as u32
-            } else if cols > 0 {
-                cols * 10
-            } else {
-                fallback.0
-            };
-
-            let height = if size.height > 0 {
-                let h = size.height as u32;
-                // adjust for prompt line and padding if we have column info
-                if cols > 0 { h * (rows.saturating_sub(2)) / rows } else { h }
-            } else if rows > 0 {
-                (rows.saturating_sub(2)) * 20
+            // many terminals leave the pixel fields of TIOCGWINSZ empty, but answer queries
+            let measured = if size.width > 0 && size.height > 0 {
+                Some((size.width as u32, size.height as u32))
             } else {
-                fallback.1
+                query_pixel_size()
+                    .or_else(|| query_cell_size().map(|(width, height)| (width * cols, height * rows)))
+                    .filter(|&(width, height)| width > 0 && height > 0)
             };
 
-            (width, height)
+            match measured {
+                Some((width, height)) => {
+                    // adjust for prompt line and padding if we have row info
+                    let height = (height * rows.saturating_sub(reserve_rows)).checked_div(rows).unwrap_or(height);
+                    (width, height)
+                }
+                None => (
+                    if cols > 0 { cols * 10 } else { fallback.0 },
+                    if rows > 0 { rows.saturating_sub(reserve_rows) * 20 } else { fallback.1 },
+                ),
+            }
         }
         Err(_) => fallback,
     }
 }
 
-/// Parses a hex string (e.g., "#FFFFFF" or "FFFFFF") into an Rgba color.
+/// Detects the terminal cell size in pixels with fallbacks.
+/// Non-integer cell sizes (window size divided by columns and rows) are rounded down.
+pub fn get_cell_size() -> (u32, u32) {
+    // same cell size as assumed by get_term_size
+    let fallback = (10, 20);
+
+    match crossterm::terminal::window_size() {
+        Ok(size) if size.columns > 0 && size.rows > 0 && size.width > 0 && size.height > 0 => (
+            (size.width / size.columns) as u32,
+            (size.height / size.rows) as u32,
+        ),
+        _ => query_cell_size().unwrap_or(fallback),
+    }
+}
+
+/// How long to wait for the terminal to answer a size query.
+const TERM_QUERY_TIMEOUT_MS: i32 = 100;
+
+/// Queries the text area size in pixels (`CSI 14 t`) as (width, height).
+fn query_pixel_size() -> Option<(u32, u32)> {
+    parse_window_report(&query_terminal("\x1b[14t", |response| response.ends_with(b"t"))?, 4)
+}
+
+/// Queries the cell size in pixels (`CSI 16 t`) as (width, height).
+fn query_cell_size() -> Option<(u32, u32)> {
+    parse_window_report(&query_terminal("\x1b[16t", |response| response.ends_with(b"t"))?, 6)
+}
+
+/// Sends a query to the controlling terminal and collects the answer until `done` or a timeout.
+#[cfg(unix)]
+fn query_terminal(query: &str, done: impl Fn(&[u8]) -> bool) -> Option<Vec<u8>> {
+    use std::io::Write;
+    use std::os::fd::AsRawFd;
+
+    let mut tty = std::fs::OpenOptions::new().read(true).write(true).open("/dev/tty").ok()?;
+    // raw mode, so the answer is neither echoed nor line buffered
+    let was_raw = crossterm::terminal::is_raw_mode_enabled().ok()?;
+    if !was_raw {
+        crossterm::terminal::enable_raw_mode().ok()?;
+    }
+
+    let mut response = Vec::new();
+    if tty.write_all(query.as_bytes()).and_then(|_| tty.flush()).is_ok() {
+        let mut fds = libc::pollfd { fd: tty.as_raw_fd(), events: libc::POLLIN, revents: 0 };
+        // give up when the terminal stays silent
+        while !done(&response) && response.len() < 256 {
+            // SAFETY: fds is a single valid pollfd for the open tty
+            if unsafe { libc::poll(&mut fds, 1, TERM_QUERY_TIMEOUT_MS) } <= 0 {
+                break;
+            }
+            let mut buf = [0u8; 64];
+            match tty.read(&mut buf) {
+                Ok(n) if n > 0 => response.extend_from_slice(&buf[..n]),
+                _ => break,
+            }
+        }
+    }
+
+    if !was_raw {
+        let _ = crossterm::terminal::disable_raw_mode();
+    }
+    Some(response)
+}
+
+#[cfg(not(unix))]
+fn query_terminal(_query: &str, _done: impl Fn(&[u8]) -> bool) -> Option<Vec<u8>> {
+    None
+}
+
+/// Parses a window report (`CSI code ; height ; width t`) into (width, height).
+fn parse_window_report(response: &[u8], code: u32) -> Option<(u32, u32)> {
+    let response = std::str::from_utf8(response).ok()?;
+    let report = response.rsplit("\x1b[").next()?.strip_suffix('t')?;
+    let mut fields = report.split(';').map(|field| field.parse::<u32>().ok());
+    match (fields.next()?, fields.next()?, fields.next()?, fields.next()) {
+        (Some(c), Some(height), Some(width), None) if c == code => Some((width, height)),
+        _ => None,
+    }
+}
+
+/// Graphics protocols supported by the terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct TermCapabilities {
+    pub kitty: bool,
+    pub sixel: bool,
+    pub iterm2: bool,
+}
+
+/// Detects the supported graphics protocols by querying the terminal.
+pub fn detect_capabilities() -> TermCapabilities {
+    // a kitty graphics query for a 1x1 image, followed by primary device attributes,
+    // which every terminal answers, so terminals without kitty graphics do not time out
+    let query = "\x1b_Gi=31,s=1,v=1,a=q,t=d,f=24;AAAA\x1b\\\x1b[c";
+    let response = query_terminal(query, |response| {
+        response.windows(3).any(|w| w == b"\x1b[?") && response.ends_with(b"c")
+    })
+    .unwrap_or_default();
+    let programs: Vec<String> = ["TERM_PROGRAM", "LC_TERMINAL"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .collect();
+    parse_capabilities(&response, &programs)
+}
+
+/// Parses the answers to the kitty graphics and device attributes queries.
+/// iTerm2 support cannot be queried and is derived from `$TERM_PROGRAM` or `$LC_TERMINAL`.
+fn parse_capabilities(response: &[u8], programs: &[String]) -> TermCapabilities {
+    let response = String::from_utf8_lossy(response);
+    let sixel = response
+        .split("\x1b[?")
+        .nth(1)
+        .and_then(|attributes| attributes.strip_suffix('c'))
+        .is_some_and(|attributes| attributes.split(';').any(|attribute| attribute == "4"));
+    TermCapabilities {
+        kitty: response.contains("\x1b_Gi=31;OK"),
+        sixel,
+        iterm2: programs.iter().any(|program| matches!(program.as_str(), "iTerm.app" | "iTerm2" | "WezTerm")),
+    }
+}
+
+/// Whether a terminal with the given `$TERM` can be expected to display graphics.
+/// Dumb terminals and the Linux console cannot.
+pub fn graphics_supported(term: Option<&str>) -> bool {
+    !matches!(term, None | Some("" | "dumb" | "linux"))
+}
+
+/// Picks the mode for `--mode auto` from `$TERM`, `$TERM_PROGRAM` and `$KITTY_WINDOW_ID`.
+/// Returns `None` for unknown terminals, whose protocols have to be queried.
+pub fn mode_from_env(term: Option<&str>, term_program: Option<&str>, kitty_window: bool) -> Option<Mode> {
+    if kitty_window || term.is_some_and(|term| term.contains("kitty") || term.contains("ghostty")) {
+        return Some(Mode::Png);
+    }
+    // WezTerm only enables kitty graphics on request
+    if matches!(term_program, Some("iTerm.app" | "WezTerm")) {
+        return Some(Mode::Iterm2);
+    }
+    if !graphics_supported(term) {
+        return Some(Mode::Blocks);
+    }
+    None
+}
+
+/// Picks the best mode the queried terminal supports, half blocks if there is none.
+pub fn mode_from_capabilities(caps: TermCapabilities) -> Mode {
+    if caps.kitty {
+        Mode::Png
+    } else if caps.iterm2 {
+        Mode::Iterm2
+    } else if caps.sixel {
+        Mode::Sixel
+    } else {
+        Mode::Blocks
+    }
+}
+
+/// Parses a CSS color name (e.g., "cornflowerblue"), a functional notation
+/// (e.g., "rgb(255, 0, 0)" or "rgba(255, 0, 0, 0.5)") or a hex string
+/// (e.g., "#FFFFFF", "FFFFFF" or the shorthand "#FFF") into an Rgba color.
 pub fn parse_color(color: &str) -> Result<Rgba<u8>> {
+    if let Some((_, rgba)) = NAMED_COLORS
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(color))
+    {
+        return Ok(*rgba);
+    }
+
+    let lower = color.trim().to_ascii_lowercase();
+    if let Some(args) = lower
+        .strip_prefix("rgba(")
+        .or_else(|| lower.strip_prefix("rgb("))
+    {
+        let args = args
+            .strip_suffix(')')
+            .context("Invalid color format: missing closing parenthesis")?;
+        return parse_rgb_components(args);
+    }
+
     let hex = color.trim_start_matches('#');
-    
-    if hex.len() != 6 {
-        anyhow::bail!("Invalid color format: must be 6 hex characters (e.g. #FFFFFF)");
+
+    // checked before slicing, which counts bytes, not characters
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        anyhow::bail!("Invalid color format: expected hex digits (e.g. #FFFFFF)");
+    }
+    if hex.len() != 3 && hex.len() != 6 {
+        anyhow::bail!("Invalid color format: must be 3 or 6 hex characters (e.g. #FFFFFF)");
     }
 
+    // expand shorthand (e.g. "F00" -> "FF0000")
+    let expanded: String;
+    let hex = if hex.len() == 3 {
+        expanded = hex.chars().flat_map(|c| [c, c]).collect();
+        expanded.as_str()
+    } else {
+        hex
+    };
+
     let r = u8::from_str_radix(&hex[0..2], 16).context("Invalid Red component")?;
     let g = u8::from_str_radix(&hex[2..4], 16).context("Invalid Green component")?;
     let b = u8::from_str_radix(&hex[4..6], 16).context("Invalid Blue component")?;
@@ -125,6 +575,118 @@ pub fn parse_color(color: &str) -> Result<Rgba<u8>> {
     Ok(Rgba([r, g, b, 255]))
 }
 
+/// Parses a gradient string of two colors separated by a dash (e.g., "#000-white").
+/// Colors may contain dashes themselves (e.g. "rgb(-1, 0, 0)"), so every dash is tried as the separator.
+pub fn parse_gradient(gradient: &str) -> Result<(Rgba<u8>, Rgba<u8>)> {
+    let mut first_error = None;
+    for (index, _) in gradient.match_indices('-') {
+        let (from, to) = (&gradient[..index], &gradient[index + 1..]);
+        match parse_color(from.trim()).and_then(|from| Ok((from, parse_color(to.trim())?))) {
+            Ok(colors) => return Ok(colors),
+            Err(e) => {
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+    match first_error {
+        Some(e) => Err(e.context("Invalid gradient: no dash separates two valid colors")),
+        None => anyhow::bail!("Invalid gradient format: expected FROM-TO (e.g. #000000-#FFFFFF)"),
+    }
+}
+
+/// Parses a page layout string ("vertical", "horizontal" or "grid:COLS").
+pub fn parse_page_layout(layout: &str) -> Result<PageLayout> {
+    match layout.trim().to_lowercase().as_str() {
+        "vertical" => Ok(PageLayout::Vertical),
+        "horizontal" => Ok(PageLayout::Horizontal),
+        other => {
+            let cols = other
+                .strip_prefix("grid:")
+                .context("Invalid page layout: expected vertical, horizontal or grid:COLS")?;
+            match cols.parse::<u32>() {
+                Ok(cols) if cols > 0 => Ok(PageLayout::Grid(cols)),
+                _ => anyhow::bail!("Invalid page layout: grid columns must be a positive integer"),
+            }
+        }
+    }
+}
+
+/// Parses a viewport size string (e.g., "1280x720").
+pub fn parse_viewport(viewport: &str) -> Result<(u32, u32)> {
+    let (width, height) = viewport
+        .split_once(['x', 'X'])
+        .context("Invalid viewport format: expected WxH (e.g. 1280x720)")?;
+    let width: u32 = width.trim().parse().context("Invalid viewport width")?;
+    let height: u32 = height.trim().parse().context("Invalid viewport height")?;
+    if width == 0 || height == 0 {
+        anyhow::bail!("Viewport width and height must be > 0");
+    }
+    Ok((width, height))
+}
+
+/// Parses a crop rectangle string ("X,Y,W,H" in source pixels).
+pub fn parse_crop(crop: &str) -> Result<(u32, u32, u32, u32)> {
+    let values = crop
+        .split(',')
+        .map(|value| value.trim().parse::<u32>())
+        .collect::<Result<Vec<_>, _>>()
+        .context("Invalid crop format: expected X,Y,W,H (e.g. 0,0,100,50)")?;
+    let [x, y, width, height] = values[..] else {
+        anyhow::bail!("Invalid crop format: expected X,Y,W,H (e.g. 0,0,100,50)");
+    };
+    if width == 0 || height == 0 {
+        anyhow::bail!("Crop width and height must be > 0");
+    }
+    Ok((x, y, width, height))
+}
+
+/// Parses a capture format string ("png", "jpeg[:QUALITY]" or "webp[:QUALITY]").
+pub fn parse_capture_format(format: &str) -> Result<CaptureFormat> {
+    let format = format.trim().to_lowercase();
+    let (name, quality) = match format.split_once(':') {
+        Some((name, quality)) => {
+            let quality: u32 = quality.trim().parse().context("Invalid capture quality")?;
+            if quality > 100 {
+                anyhow::bail!("Capture quality must be between 0 and 100");
+            }
+            (name, Some(quality))
+        }
+        None => (format.as_str(), None),
+    };
+    match (name, quality) {
+        ("png", None) => Ok(CaptureFormat::Png),
+        ("png", Some(_)) => anyhow::bail!("Capture quality is only supported for jpeg and webp"),
+        ("jpeg" | "jpg", quality) => Ok(CaptureFormat::Jpeg(quality)),
+        ("webp", quality) => Ok(CaptureFormat::Webp(quality)),
+        _ => anyhow::bail!("Invalid capture format: expected png, jpeg or webp"),
+    }
+}
+
+/// Parses the comma-separated components of `rgb(...)`/`rgba(...)`.
+/// Channels are integers clamped to 0-255, alpha is a float clamped to 0.0-1.0.
+fn parse_rgb_components(args: &str) -> Result<Rgba<u8>> {
+    let parts: Vec<&str> = args.split(',').map(str::trim).collect();
+    if parts.len() != 3 && parts.len() != 4 {
+        anyhow::bail!("Invalid color format: rgb() requires 3 or 4 components");
+    }
+
+    let mut rgba = [0u8, 0, 0, 255];
+    for (channel, part) in rgba.iter_mut().zip(&parts[..3]) {
+        let value: i64 = part.parse().context("Invalid color channel")?;
+        *channel = value.clamp(0, 255) as u8;
+    }
+
+    if let Some(alpha) = parts.get(3) {
+        let alpha: f32 = alpha.parse().context("Invalid alpha component")?;
+        if !alpha.is_finite() {
+            anyhow::bail!("Invalid alpha component");
+        }
+        rgba[3] = (alpha.clamp(0.0, 1.0) * 255.0).round() as u8;
+    }
+
+    Ok(Rgba(rgba))
+}
+
 /// Calculates the final dimensions of the image based on the ResizeMode and Terminal Size.
 pub fn calculate_dimensions(
     img_dims: (u32, u32),
@@ -159,23 +721,88 @@ pub fn calculate_dimensions(
             if th > 0.0 { scale_to_height(th) } else { (w, h) }
         },
 
-        ResizeMode::Manual { width, height } => match (width, height) {
-            (Some(target_w), Some(target_h)) => (target_w as f64, target_h as f64),
+        ResizeMode::Manual { width, height, fit } => match (width, height) {
+            (Some(target_w), Some(target_h)) => {
+                let (bw, bh) = (target_w as f64, target_h as f64);
+                match fit {
+                    // render_image pads letterboxed images to the box
+                    Fit::Contain | Fit::Letterbox => {
+                        let ratio = (bw / w).min(bh / h);
+                        (w * ratio, h * ratio)
+                    }
+                    // render_image crops the overflow to the box
+                    Fit::Cover => {
+                        let ratio = (bw / w).max(bh / h);
+                        ((w * ratio).max(bw), (h * ratio).max(bh))
+                    }
+                    Fit::Stretch => (bw, bh),
+                }
+            }
             (Some(target_w), None) => scale_to_width(target_w as f64),
             (None, Some(target_h)) => scale_to_height(target_h as f64),
             (None, None) => (w, h), // should not happen
         },
+
+        ResizeMode::Thumbnail(max_side) => {
+            let ratio = (max_side as f64 / w.max(h)).min(1.0);
+            (w * ratio, h * ratio)
+        },
     };
 
     (final_w.round() as u32, final_h.round() as u32)
 }
 
-/// Parse a 1-indexed pages string (e.g., "1-3,5") to 0-indexed vector.
-pub fn parse_pages(pages: &str) -> Result<Option<Vec<u16>>> {
-    if pages.trim().is_empty() {
+/// Scales dimensions down to fit within the given maximum width and height, preserving aspect ratio.
+/// Dimensions within the limits are returned unchanged.
+pub fn clamp_dimensions(dims: (u32, u32), max_width: Option<u32>, max_height: Option<u32>) -> (u32, u32) {
+    let (w, h) = (dims.0 as f64, dims.1 as f64);
+    let ratio_w = max_width.map_or(1.0, |max| max as f64 / w);
+    let ratio_h = max_height.map_or(1.0, |max| max as f64 / h);
+    let ratio = ratio_w.min(ratio_h);
+    if ratio >= 1.0 || w == 0.0 || h == 0.0 {
+        return dims;
+    }
+    (((w * ratio).round() as u32).max(1), ((h * ratio).round() as u32).max(1))
+}
+
+/// A range of pages, 0-indexed and inclusive, selecting every `step`th page.
+/// Open ends select the first or last page (both open is all pages),
+/// ranges with `start > end` select pages in reverse order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PageRange {
+    pub start: Option<u32>,
+    pub end: Option<u32>,
+    pub step: u32,
+}
+
+impl PageRange {
+    /// All pages of the document.
+    pub const ALL: PageRange = PageRange { start: None, end: None, step: 1 };
+}
+
+impl From<u32> for PageRange {
+    /// A single page.
+    fn from(index: u32) -> Self {
+        PageRange { start: Some(index), end: Some(index), step: 1 }
+    }
+}
+
+/// Parse a 1-indexed pages string (e.g., "1-3,5", "3-", "-4", "5-1" or "1-10:2") to 0-indexed ranges.
+/// An empty string or "all" selects all pages, "all" can also be part of a list.
+pub fn parse_pages(pages: &str) -> Result<Option<Vec<PageRange>>> {
+    if pages.trim().is_empty() || pages.trim().eq_ignore_ascii_case("all") {
         return Ok(None);
     }
 
+    // 1-indexed page number to 0-indexed
+    let parse_page = |page: &str, name: &str| -> Result<u32> {
+        let index: u32 = page.parse().with_context(|| format!("Invalid page {}", name))?;
+        if index < 1 {
+            anyhow::bail!("Page numbers must be >= 1");
+        }
+        Ok(index - 1)
+    };
+
     let mut result = Vec::new();
 
     for part in pages.split(',') {
@@ -184,30 +811,100 @@ pub fn parse_pages(pages: &str) -> Result<Option<Vec<u16>>> {
             continue;
         }
 
-        if let Some((start_str, end_str)) = part.split_once('-') {
-            let start: u16 = start_str.trim().parse().context("Invalid page range start")?;
-            let end: u16 = end_str.trim().parse().context("Invalid page range end")?;
-
-            if start < 1 || end <= start {
-                anyhow::bail!("Page range must start >= 1 and end > start");
+        if part.eq_ignore_ascii_case("all") {
+            result.push(PageRange::ALL);
+        } else if let Some((start_str, end_str)) = part.split_once('-') {
+            let (start_str, end_str) = (start_str.trim(), end_str.trim());
+            // optional step suffix, e.g. "1-10:2"
+            let (end_str, step) = match end_str.split_once(':') {
+                Some((end_str, step_str)) => {
+                    let step: i64 = step_str.trim().parse().context("Invalid page step")?;
+                    if step < 1 {
+                        anyhow::bail!("Page step must be >= 1");
+                    }
+                    (end_str.trim(), u32::try_from(step).context("Invalid page step")?)
+                }
+                None => (end_str, 1),
+            };
+            if start_str.is_empty() && end_str.is_empty() {
+                anyhow::bail!("Page range needs a start or an end");
             }
-            result.extend((start..=end).map(|i| i - 1));
+            let start = (!start_str.is_empty()).then(|| parse_page(start_str, "range start")).transpose()?;
+            let end = (!end_str.is_empty()).then(|| parse_page(end_str, "range end")).transpose()?;
+            result.push(PageRange { start, end, step });
         } else {
-            let index: u16 = part.parse().context("Invalid page index")?;
-            if index < 1 {
-                anyhow::bail!("Page index must be >= 1");
-            }
-            result.push(index - 1);
+            result.push(PageRange::from(parse_page(part, "index")?));
         }
     }
 
-    if result.is_empty() {
-        Ok(None)
-    } else {
+    Ok((!result.is_empty()).then_some(result))
+}
+
+/// Expands page ranges to 0-indexed pages of a document with `n_pages` pages.
+/// The pages are sorted and deduplicated, unless `keep_order` keeps them as given, duplicates included.
+/// Selections of more than `max_pages` pages are rejected before they are expanded.
+pub fn resolve_pages(ranges: &[PageRange], n_pages: u32, keep_order: bool, max_pages: u32) -> Result<Vec<u32>> {
+    let mut result: Vec<u32> = Vec::new();
+    for range in ranges {
+        let start = range.start.unwrap_or(0);
+        let end = range.end.unwrap_or(n_pages.saturating_sub(1));
+        // page indices are 0-indexed, report them as the user typed them
+        if let Some(page) = [start, end].into_iter().find(|&page| page >= n_pages) {
+            anyhow::bail!("Page {} out of range (must be between 1 and {})", page as u64 + 1, n_pages);
+        }
+        let step = range.step.max(1);
+        let count = start.abs_diff(end) / step + 1;
+        if result.len() as u64 + count as u64 > max_pages as u64 {
+            anyhow::bail!("Page selection has more than {} pages", max_pages);
+        }
+        let pages: Box<dyn Iterator<Item = u32>> = if start <= end {
+            Box::new((start..=end).step_by(step as usize))
+        } else {
+            Box::new((end..=start).rev().step_by(step as usize))
+        };
+        result.extend(pages);
+    }
+    if !keep_order {
         result.sort_unstable();
         result.dedup();
-        Ok(Some(result))
     }
+    Ok(result)
+}
+
+/// Whether files with this extension are picked up when walking directories with --recursive.
+pub fn is_supported_extension(extension: &str) -> bool {
+    let extension = extension.to_lowercase();
+    #[cfg(feature = "video")]
+    if VIDEO_EXTENSIONS.contains(&extension.as_str()) {
+        return true;
+    }
+    ImageFormat::from_extension(&extension).is_some()
+        || matches!(extension.as_str(), "svg" | "pdf" | "html" | "htm")
+        || OFFICE_EXTENSIONS.contains(&extension.as_str())
+        || PLUGINS.get_or_init(load_plugins).values().any(|plugin| plugin.extensions.contains(&extension))
+}
+
+/// Collects the supported files below the directory, sorted by path.
+pub fn walk_dir(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    collect_files(dir, &mut files)?;
+    files.sort();
+    Ok(files)
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    let entries = std::fs::read_dir(dir).with_context(|| format!("Failed to read directory: {}", dir.display()))?;
+    for entry in entries {
+        let entry = entry?;
+        let path = entry.path();
+        // symlinked directories are not followed, so there are no cycles
+        if entry.file_type()?.is_dir() {
+            collect_files(&path, files)?;
+        } else if path.is_file() && path.extension().and_then(|e| e.to_str()).is_some_and(is_supported_extension) {
+            files.push(path);
+        }
+    }
+    Ok(())
 }
 
 pub fn load_file(ctx: &KvContext, path: &Path) -> Result<LoadResult> {
@@ -221,6 +918,10 @@ pub fn load_file(ctx: &KvContext, path: &Path) -> Result<LoadResult> {
     {
         // string conversion for URL check
         let path_lossy = path.to_string_lossy();
+        #[cfg(feature = "http")]
+        if is_download_url(ctx, &path_lossy) {
+            return load_url(ctx, &path_lossy);
+        }
         if is_html(ctx, &extension, path_lossy.as_bytes()) {
             // use the bytes of the path string strictly for HTML rendering
             let img = render_html_chrome(ctx, path_lossy.as_bytes())?;
@@ -228,6 +929,9 @@ pub fn load_file(ctx: &KvContext, path: &Path) -> Result<LoadResult> {
         }
     }
 
+    if path.is_dir() {
+        anyhow::bail!("Is a directory, use --recursive to show the files in it");
+    }
     let mut file = File::open(path).with_context(|| format!("Failed to open file: {}", path.display()))?;
     let mut data = Vec::new();
     file.read_to_end(&mut data)?;
@@ -235,22 +939,199 @@ pub fn load_file(ctx: &KvContext, path: &Path) -> Result<LoadResult> {
     load_data(ctx, &data, &extension)
 }
 
-pub fn load_data(ctx: &KvContext, data: &[u8], extension: &str) -> Result<LoadResult> {
+/// Maximum size of downloaded files.
+#[cfg(feature = "http")]
+const MAX_DOWNLOAD_SIZE: u64 = 256 * 1024 * 1024;
+
+/// Returns the lowercase file extension of a URL path, ignoring the query and fragment.
+#[cfg(feature = "http")]
+fn url_extension(url: &str) -> String {
+    let path = url.split(['?', '#']).next().unwrap_or("");
+    let name = path.split("://").nth(1).and_then(|rest| rest.split_once('/')).map_or("", |(_, p)| p);
+    let name = name.rsplit('/').next().unwrap_or("");
+    name.rsplit_once('.').map_or("", |(_, ext)| ext).to_lowercase()
+}
+
+/// Whether the input is a remote URL that should be downloaded instead of opened in Chrome.
+#[cfg(feature = "http")]
+fn is_download_url(ctx: &KvContext, s: &str) -> bool {
+    ctx.input_type != InputType::Html
+        && (s.starts_with("http://") || s.starts_with("https://"))
+        && !matches!(url_extension(s).as_str(), "html" | "htm")
+}
+
+/// Downloads the URL and loads it like a file, HTML pages are still rendered with Chrome.
+#[cfg(feature = "http")]
+fn load_url(ctx: &KvContext, url: &str) -> Result<LoadResult> {
+    let mut response = ureq::get(url)
+        .call()
+        .with_context(|| format!("Failed to download {}", url))?;
+    let content_type = response
+        .headers()
+        .get(ureq::http::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("");
+    if content_type.starts_with("text/html") || content_type.starts_with("application/xhtml") {
+        return Ok(LoadResult::Image(render_html_chrome(ctx, url.as_bytes())?));
+    }
+
+    let data = response
+        .body_mut()
+        .with_config()
+        .limit(MAX_DOWNLOAD_SIZE)
+        .read_to_vec()
+        .with_context(|| format!("Failed to download {}", url))?;
+    load_data(ctx, &data, &url_extension(url))
+}
+
+/// Whether the data is a video, by extension or magic bytes (ISO media or Matroska/WebM).
+#[cfg(feature = "video")]
+fn is_video(data: &[u8], extension: &str) -> bool {
+    let iso_media = data.len() >= 12 && &data[4..8] == b"ftyp" && sniff_image_format(data).is_none();
+    VIDEO_EXTENSIONS.contains(&extension) || iso_media || data.starts_with(b"\x1A\x45\xDF\xA3")
+}
+
+/// Compatible brands of the `ftyp` box, which follow the major brand and minor version.
+fn ftyp_compatible_brands(data: &[u8]) -> impl Iterator<Item = &[u8]> {
+    let size = u32::from_be_bytes([data[0], data[1], data[2], data[3]]) as usize;
+    data.get(16..size.min(data.len())).unwrap_or_default().chunks_exact(4)
+}
+
+/// Image container formats detected by their magic bytes, with the matching `image` format if any.
+fn sniff_image_format(data: &[u8]) -> Option<(&'static str, Option<ImageFormat>)> {
+    // ISO base media files start with a `ftyp` box followed by the major brand
+    if data.len() >= 12 && &data[4..8] == b"ftyp" {
+        const AVIF: Option<(&str, Option<ImageFormat>)> = Some(("AVIF", Some(ImageFormat::Avif)));
+        return match &data[8..12] {
+            b"avif" | b"avis" => AVIF,
+            // generic HEIF brands, used by AVIF files too, which list avif in the compatible brands
+            b"mif1" | b"msf1"
+                if ftyp_compatible_brands(data).any(|brand| brand == b"avif" || brand == b"avis") =>
+            {
+                AVIF
+            }
+            b"heic" | b"heix" | b"hevc" | b"hevx" | b"heim" | b"heis" | b"mif1" | b"msf1" => {
+                Some(("HEIC", None))
+            }
+            _ => None,
+        };
+    }
+    if data.starts_with(b"\xFF\x0A") || data.starts_with(b"\x00\x00\x00\x0CJXL \x0D\x0A\x87\x0A") {
+        return Some(("JPEG XL", None));
+    }
+    if data.starts_with(b"\x00\x00\x01\x00") {
+        return Some(("ICO", Some(ImageFormat::Ico)));
+    }
+    None
+}
+
+/// Decodes formats detected by `sniff_image_format`, with a clear error if they are not supported.
+fn load_sniffed_image(
+    ctx: &KvContext,
+    data: &[u8],
+    name: &str,
+    format: Option<ImageFormat>,
+) -> Result<LoadResult> {
+    let Some(format) = format else {
+        anyhow::bail!("{} images are not supported", name);
+    };
+    match image::load_from_memory_with_format(data, format) {
+        Ok(img) => load_image(ctx, img, data),
+        // e.g. AVIF without the native decoder
+        Err(image::ImageError::Unsupported(_)) => anyhow::bail!("{} images are not supported", name),
+        Err(err) => Err(err).with_context(|| format!("Failed to decode {} image", name)),
+    }
+}
+
+/// Renders a decoded still image, keeping the original bytes of PNGs that need no changes.
+fn load_image(ctx: &KvContext, img: DynamicImage, data: &[u8]) -> Result<LoadResult> {
+    let size = (img.width(), img.height());
+    let image = render_image(ctx, img)?;
+    if data.starts_with(PNG_MAGIC) && !ctx.changes_pixels() && (image.width(), image.height()) == size {
+        return Ok(LoadResult::Png { image, data: data.to_vec() });
+    }
+    Ok(LoadResult::Image(image))
+}
+
+/// Animated image formats, detected by `detect_input` and decoded by `decode_frames`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AnimationFormat {
+    Gif,
+    Apng,
+    WebP,
+}
+
+/// What `detect_input` found, `Unknown` inputs are decoded as images, file paths or text.
+enum InputKind {
+    Text,
+    Plugin(&'static str, &'static Plugin),
+    Animation(AnimationFormat),
+    #[cfg(feature = "video")]
+    Video,
+    Sniffed(&'static str, Option<ImageFormat>),
+    Image,
+    Svg,
+    Pdf,
+    Office,
+    #[cfg(feature = "http")]
+    Url(String),
+    Html,
+    Unknown,
+}
+
+/// Detects how the data is rendered, shared by `load_data` and `inspect_data`, so --info
+/// reports what would be shown. The order matters, the first match wins.
+fn detect_input(ctx: &KvContext, data: &[u8], extension: &str) -> Result<InputKind> {
     if ctx.input_type == InputType::Text {
-        return Ok(LoadResult::Data(data.to_vec()));
+        return Ok(InputKind::Text);
     }
 
     let plugins = PLUGINS.get_or_init(load_plugins);
-
-    for plugin in plugins.values() {
+    for (name, plugin) in plugins {
         if has_extension_or_magic_bytes(data, extension, plugin.magic_bytes.as_ref().unwrap_or(&vec![]), &plugin.extensions) {
-            return Ok(LoadResult::Image(render_plugin(ctx, data, plugin)?));
+            return Ok(InputKind::Plugin(name, plugin));
+        }
+    }
+
+    if matches!(ctx.input_type, InputType::Auto | InputType::Image) {
+        // a second frame is enough to tell, single-frame animations keep the still image path
+        let format = if data.starts_with(b"GIF8") {
+            let decoder = GifDecoder::new(Cursor::new(data)).context("Failed to decode GIF")?;
+            (decoder.into_frames().take(2).count() > 1).then_some(AnimationFormat::Gif)
+        } else if data.starts_with(PNG_MAGIC) {
+            let decoder = PngDecoder::new(Cursor::new(data)).context("Failed to decode PNG")?;
+            // only APNGs (with an acTL chunk) leave the PNG fast path
+            if decoder.is_apng().context("Failed to decode PNG")? {
+                let decoder = decoder.apng().context("Failed to decode APNG")?;
+                (decoder.into_frames().take(2).count() > 1).then_some(AnimationFormat::Apng)
+            } else {
+                None
+            }
+        } else if data.starts_with(b"RIFF") && data.get(8..12) == Some(b"WEBP") {
+            let decoder = WebPDecoder::new(Cursor::new(data)).context("Failed to decode WebP")?;
+            decoder.has_animation().then_some(AnimationFormat::WebP)
+        } else {
+            None
+        };
+        if let Some(format) = format {
+            return Ok(InputKind::Animation(format));
+        }
+    }
+
+    #[cfg(feature = "video")]
+    if matches!(ctx.input_type, InputType::Auto) && is_video(data, extension) {
+        return Ok(InputKind::Video);
+    }
+
+    // sniff before the extension checks, extensions might lie
+    if matches!(ctx.input_type, InputType::Auto | InputType::Image) {
+        if let Some((name, format)) = sniff_image_format(data) {
+            return Ok(InputKind::Sniffed(name, format));
         }
     }
 
     if ctx.input_type == InputType::Image {
-        let img = image::load_from_memory(data).context("Failed to load image")?;
-        return Ok(LoadResult::Image(render_image(ctx, img)?));
+        return Ok(InputKind::Image);
     }
 
     if ctx.input_type == InputType::Svg
@@ -258,28 +1139,262 @@ pub fn load_data(ctx: &KvContext, data: &[u8], extension: &str) -> Result<LoadRe
         || data.starts_with(b"<svg")
         || data.starts_with(b"<?xml")
     {
-        return Ok(LoadResult::Image(render_svg(ctx, data)?));
+        return Ok(InputKind::Svg);
     }
 
     if ctx.input_type == InputType::Pdf || extension == "pdf" || data.starts_with(b"%PDF") {
-        return Ok(LoadResult::Image(render_pdf(ctx, data)?));
+        return Ok(InputKind::Pdf);
     }
-    if ctx.input_type == InputType::Office
-        || ["doc", "docx", "xls", "xlsx", "ppt", "pptx"].contains(&extension)
-    {
-        return Ok(LoadResult::Image(render_office(ctx, data, extension)?));
+    if ctx.input_type == InputType::Office || OFFICE_EXTENSIONS.contains(&extension) {
+        return Ok(InputKind::Office);
     }
 
-    if is_html(ctx, extension, data)
-        || data.starts_with(b"<html")
-        || data.starts_with(b"<!DOCTYPE html")
+    #[cfg(feature = "http")]
+    if let Some(url) = std::str::from_utf8(data).ok().map(str::trim) {
+        if is_download_url(ctx, url) {
+            return Ok(InputKind::Url(url.to_string()));
+        }
+    }
+
+    if is_html(ctx, extension, data) || data.starts_with(b"<html") || data.starts_with(b"<!DOCTYPE html") {
+        return Ok(InputKind::Html);
+    }
+
+    Ok(InputKind::Unknown)
+}
+
+/// Decodes all frames of an animation found by `detect_input`.
+fn decode_frames(data: &[u8], format: AnimationFormat) -> Result<Vec<image::Frame>> {
+    match format {
+        AnimationFormat::Gif => GifDecoder::new(Cursor::new(data))
+            .and_then(|decoder| decoder.into_frames().collect_frames())
+            .context("Failed to decode GIF"),
+        AnimationFormat::Apng => PngDecoder::new(Cursor::new(data))
+            .and_then(|decoder| decoder.apng())
+            .and_then(|decoder| decoder.into_frames().collect_frames())
+            .context("Failed to decode APNG"),
+        AnimationFormat::WebP => WebPDecoder::new(Cursor::new(data))
+            .and_then(|decoder| decoder.into_frames().collect_frames())
+            .context("Failed to decode WebP"),
+    }
+}
+
+/// What an input would be rendered as, reported by --info without rendering it.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct InputInfo {
+    /// One of image, animation, svg, pdf, office, html, url, video, plugin or text.
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    /// Image format or plugin name.
+    pub format: Option<String>,
+    /// Source size in pixels, PDF page sizes in points.
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// Size of the rendered image, for images and SVGs.
+    pub target_width: Option<u32>,
+    pub target_height: Option<u32>,
+    pub pages: Option<u32>,
+}
+
+impl InputInfo {
+    fn new(kind: &'static str) -> Self {
+        Self { kind, format: None, width: None, height: None, target_width: None, target_height: None, pages: None }
+    }
+
+    fn with_size(mut self, ctx: &KvContext, size: (u32, u32)) -> Self {
+        let (target_width, target_height) = target_dimensions(ctx, size);
+        self.width = Some(size.0);
+        self.height = Some(size.1);
+        self.target_width = Some(target_width);
+        self.target_height = Some(target_height);
+        self
+    }
+}
+
+/// Size `render_image` produces for a source image of the given size.
+pub fn target_dimensions(ctx: &KvContext, size: (u32, u32)) -> (u32, u32) {
+    let size = ctx.crop.map_or(size, |(_, _, width, height)| (width, height));
+    let size = if ctx.rotate % 180 == 90 { (size.1, size.0) } else { size };
+    let dims = clamp_dimensions(
+        calculate_dimensions(size, ctx.resize_mode, ctx.term_size),
+        ctx.max_width,
+        ctx.max_height,
+    );
+    match ctx.resize_mode {
+        ResizeMode::Manual { width: Some(box_w), height: Some(box_h), fit: Fit::Cover } => {
+            (dims.0.min(box_w), dims.1.min(box_h))
+        }
+        ResizeMode::Manual { width: Some(box_w), height: Some(box_h), fit: Fit::Letterbox } => {
+            clamp_dimensions((box_w, box_h), ctx.max_width, ctx.max_height)
+        }
+        _ => dims,
+    }
+}
+
+/// Reads the format and size from the image header, without decoding the pixels.
+fn inspect_image(ctx: &KvContext, data: &[u8], format: Option<ImageFormat>) -> Result<InputInfo> {
+    let reader = match format {
+        Some(format) => image::ImageReader::with_format(Cursor::new(data), format),
+        None => image::ImageReader::new(Cursor::new(data)).with_guessed_format()?,
+    };
+    let format = reader.format().map(|format| format!("{:?}", format).to_uppercase());
+    let size = reader.into_dimensions().context("Failed to load image")?;
+    Ok(InputInfo { format, ..InputInfo::new("image").with_size(ctx, size) })
+}
+
+/// Like `load_file`, but only detects what would be rendered.
+pub fn inspect_file(ctx: &KvContext, path: &Path) -> Result<InputInfo> {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+
+    let path_lossy = path.to_string_lossy();
+    #[cfg(feature = "http")]
+    if is_download_url(ctx, &path_lossy) {
+        return Ok(InputInfo::new("url"));
+    }
+    if is_html(ctx, &extension, path_lossy.as_bytes()) {
+        return Ok(InputInfo::new("html"));
+    }
+
+    if path.is_dir() {
+        anyhow::bail!("Is a directory, use --recursive to show the files in it");
+    }
+    let mut file = File::open(path).with_context(|| format!("Failed to open file: {}", path.display()))?;
+    let mut data = Vec::new();
+    file.read_to_end(&mut data)?;
+
+    inspect_data(ctx, &data, &extension)
+}
+
+/// Like `load_data`, with the same detection, but only reads sizes from headers.
+pub fn inspect_data(ctx: &KvContext, data: &[u8], extension: &str) -> Result<InputInfo> {
+    match detect_input(ctx, data, extension)? {
+        InputKind::Text => return Ok(InputInfo::new("text")),
+        InputKind::Plugin(name, _) => {
+            return Ok(InputInfo { format: Some(name.to_string()), ..InputInfo::new("plugin") })
+        }
+        InputKind::Animation(_) => {
+            let info = inspect_image(ctx, data, None)?;
+            return Ok(InputInfo { kind: "animation", ..info });
+        }
+        #[cfg(feature = "video")]
+        InputKind::Video => return Ok(InputInfo::new("video")),
+        InputKind::Sniffed(name, format) => {
+            return match format {
+                Some(format) => inspect_image(ctx, data, Some(format)),
+                None => anyhow::bail!("{} images are not supported", name),
+            }
+        }
+        InputKind::Image => return inspect_image(ctx, data, None),
+        InputKind::Svg => return Ok(InputInfo::new("svg").with_size(ctx, svg_size(ctx, data)?)),
+        InputKind::Pdf => {
+            let (pages, (width, height)) = pdf_size(data)?;
+            return Ok(InputInfo { width: Some(width), height: Some(height), pages: Some(pages), ..InputInfo::new("pdf") });
+        }
+        InputKind::Office => return Ok(InputInfo::new("office")),
+        #[cfg(feature = "http")]
+        InputKind::Url(_) => return Ok(InputInfo::new("url")),
+        InputKind::Html => return Ok(InputInfo::new("html")),
+        InputKind::Unknown => {}
+    }
+
+    // fallback for InputType::Auto
+    match inspect_image(ctx, data, None) {
+        Ok(info) => Ok(info),
+        Err(err) => {
+            if let Ok(text) = std::str::from_utf8(data) {
+                let path_str = text.trim();
+                if !path_str.contains('\n') && !path_str.is_empty() {
+                    let path = PathBuf::from(path_str);
+                    if path.is_file() {
+                        return inspect_file(ctx, &path);
+                    }
+                }
+                return Ok(InputInfo::new("text"));
+            }
+            Err(anyhow::anyhow!("Failed to decode input: {:#}", err))
+        }
+    }
+}
+
+/// Whether input starting with `prefix` is plain text that `load_data` would show as is,
+/// so it can be streamed without reading the rest. Anything unsure needs the full data.
+pub fn is_text_prefix(ctx: &KvContext, prefix: &[u8]) -> bool {
+    if ctx.input_type == InputType::Text {
+        return true;
+    }
+    if ctx.input_type != InputType::Auto {
+        return false;
+    }
+
+    let plugins = PLUGINS.get_or_init(load_plugins);
+    if plugins.values().any(|plugin| {
+        has_extension_or_magic_bytes(prefix, "", plugin.magic_bytes.as_ref().unwrap_or(&vec![]), &plugin.extensions)
+    }) {
+        return false;
+    }
+
+    #[cfg(feature = "video")]
+    if is_video(prefix, "") {
+        return false;
+    }
+
+    let magic: [&[u8]; 5] = [b"<svg", b"<?xml", b"%PDF", b"<html", b"<!DOCTYPE html"];
+    if magic.iter().any(|magic| prefix.starts_with(magic))
+        || sniff_image_format(prefix).is_some()
+        || image::guess_format(prefix).is_ok()
+        || is_html(ctx, "", prefix)
     {
-        return Ok(LoadResult::Image(render_html_chrome(ctx, data)?));
+        return false;
+    }
+
+    // the prefix may end inside a multi-byte character
+    let text = match std::str::from_utf8(prefix) {
+        Ok(text) => text,
+        Err(err) if err.error_len().is_none() => {
+            std::str::from_utf8(&prefix[..err.valid_up_to()]).unwrap_or_default()
+        }
+        Err(_) => return false,
+    };
+    // single lines might be file paths or URLs
+    text.trim().contains('\n')
+}
+
+pub fn load_data(ctx: &KvContext, data: &[u8], extension: &str) -> Result<LoadResult> {
+    match detect_input(ctx, data, extension)? {
+        InputKind::Text => return Ok(LoadResult::Data(data.to_vec())),
+        InputKind::Plugin(_, plugin) => return Ok(LoadResult::Image(render_plugin(ctx, data, plugin)?)),
+        InputKind::Animation(format) => {
+            let frames = decode_frames(data, format)?;
+            return Ok(LoadResult::Animation(render_frames(ctx, frames)?));
+        }
+        #[cfg(feature = "video")]
+        InputKind::Video => return Ok(LoadResult::Image(render_video(ctx, data)?)),
+        InputKind::Sniffed(name, format) => return load_sniffed_image(ctx, data, name, format),
+        InputKind::Image => {
+            let img = image::load_from_memory(data).context("Failed to load image")?;
+            return load_image(ctx, img, data);
+        }
+        InputKind::Svg => return Ok(LoadResult::Image(render_cached(ctx, data, render_svg)?)),
+        InputKind::Pdf => {
+            if ctx.separate_pages {
+                return Ok(LoadResult::Pages(render_pdf_pages(ctx, data)?));
+            }
+            return Ok(LoadResult::Image(render_cached(ctx, data, render_pdf)?));
+        }
+        InputKind::Office => return Ok(LoadResult::Image(render_office(ctx, data, extension)?)),
+        #[cfg(feature = "http")]
+        InputKind::Url(url) => return load_url(ctx, &url),
+        InputKind::Html => return Ok(LoadResult::Image(render_html_chrome(ctx, data)?)),
+        InputKind::Unknown => {}
     }
 
     // fallback for InputType::Auto
     match image::load_from_memory(data) {
-        Ok(img) => Ok(LoadResult::Image(render_image(ctx, img)?)),
+        Ok(img) => load_image(ctx, img, data),
         Err(err) => {
             // check if it's a valid UTF-8 string that points to a file path
             if let Ok(text) = std::str::from_utf8(data) {
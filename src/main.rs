@@ -1,27 +1,112 @@
-use crate::{pretty_print, send_image};
+use crate::{pretty_print, send_animation, send_image, send_png};
 use anyhow::{Context, Result};
-use clap::{Parser, ValueEnum};
+use clap::parser::ValueSource;
+use clap::{ArgGroup, ArgMatches, CommandFactory, FromArgMatches, Parser, ValueEnum};
+use image::imageops::FilterType;
+use image::DynamicImage;
 use kv::*;
-use std::io::{self, BufWriter, Read, Write};
-use std::path::PathBuf;
+use std::io::{self, BufWriter, Cursor, Read, Write};
+use std::path::{Path, PathBuf};
 use tempfile::NamedTempFile;
 
+/// Quiet time after a file change before rendering again, editors often write in several steps.
+const WATCH_DEBOUNCE_MS: u64 = 100;
+
 #[cfg(test)]
 mod tests_main;
 
-#[derive(Debug, Clone, ValueEnum, PartialEq)]
+#[derive(Debug, Clone, ValueEnum, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
 enum ModeOption {
+    /// Detect from the terminal
+    Auto,
     Png,
     Zlib,
     Raw,
+    Sixel,
+    Iterm2,
+    Blocks,
+}
+
+impl From<Mode> for ModeOption {
+    fn from(mode: Mode) -> Self {
+        match mode {
+            Mode::Png => ModeOption::Png,
+            Mode::Zlib => ModeOption::Zlib,
+            Mode::Raw => ModeOption::Raw,
+            Mode::Sixel => ModeOption::Sixel,
+            Mode::Iterm2 => ModeOption::Iterm2,
+            Mode::Blocks => ModeOption::Blocks,
+        }
+    }
 }
 
 impl From<ModeOption> for Mode {
     fn from(arg: ModeOption) -> Self {
         match arg {
-            ModeOption::Png => Mode::Png,
+            // resolved in main, kitty graphics otherwise
+            ModeOption::Auto | ModeOption::Png => Mode::Png,
             ModeOption::Zlib => Mode::Zlib,
             ModeOption::Raw => Mode::Raw,
+            ModeOption::Sixel => Mode::Sixel,
+            ModeOption::Iterm2 => Mode::Iterm2,
+            ModeOption::Blocks => Mode::Blocks,
+        }
+    }
+}
+
+#[derive(Debug, Clone, ValueEnum, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum FitOption {
+    Contain,
+    Cover,
+    Stretch,
+}
+
+impl From<FitOption> for Fit {
+    fn from(arg: FitOption) -> Self {
+        match arg {
+            FitOption::Contain => Fit::Contain,
+            FitOption::Cover => Fit::Cover,
+            FitOption::Stretch => Fit::Stretch,
+        }
+    }
+}
+
+#[derive(Debug, Clone, ValueEnum, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum FilterOption {
+    Nearest,
+    Triangle,
+    CatmullRom,
+    Gaussian,
+    Lanczos3,
+}
+
+impl From<FilterOption> for FilterType {
+    fn from(arg: FilterOption) -> Self {
+        match arg {
+            FilterOption::Nearest => FilterType::Nearest,
+            FilterOption::Triangle => FilterType::Triangle,
+            FilterOption::CatmullRom => FilterType::CatmullRom,
+            FilterOption::Gaussian => FilterType::Gaussian,
+            FilterOption::Lanczos3 => FilterType::Lanczos3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, ValueEnum, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum TransferOption {
+    Direct,
+    File,
+}
+
+impl From<TransferOption> for Transfer {
+    fn from(arg: TransferOption) -> Self {
+        match arg {
+            TransferOption::Direct => Transfer::Direct,
+            TransferOption::File => Transfer::File,
         }
     }
 }
@@ -56,16 +141,21 @@ type TempAndFinalOption = Option<(NamedTempFile, PathBuf)>;
 /// A image viewer for the Kitty Terminal Graphics Protocol.
 #[derive(Parser, Debug, Clone)]
 #[command(author, version, about, long_about = None)]
+#[command(group(ArgGroup::new("file_output").args(["output", "output_dir"])))]
 struct Config {
-    /// Input files
+    /// Input files (with the glob feature also patterns like 'photos/**/*.jpg')
     #[arg(name = "FILES")]
     files: Vec<PathBuf>,
 
+    /// Show the supported files in directories and their subdirectories, sorted by path
+    #[arg(long)]
+    recursive: bool,
+
     /// Specify image width in pixels
     #[arg(
         short = 'w',
         long,
-        conflicts_with_all = ["height", "fullwidth", "fullheight", "resize", "noresize"],
+        conflicts_with_all = ["cols", "fullwidth", "fullheight", "resize", "noresize", "thumbnail"],
     )]
     width: Option<u32>,
 
@@ -73,15 +163,43 @@ struct Config {
     #[arg(
         short = 'H', // else conflicts with --help
         long,
-        conflicts_with_all = ["width", "fullwidth", "fullheight", "resize", "noresize"],
+        conflicts_with_all = ["rows", "fullwidth", "fullheight", "resize", "noresize", "thumbnail"],
     )]
     height: Option<u32>,
 
+    /// Never distort images given both --width and --height, pad them to the box instead
+    #[arg(long)]
+    keep_aspect: bool,
+
+    /// Specify image width in terminal columns (the cell width is rounded down to whole pixels)
+    #[arg(
+        long,
+        value_name = "N",
+        conflicts_with_all = ["fullwidth", "fullheight", "resize", "noresize", "thumbnail"],
+    )]
+    cols: Option<u32>,
+
+    /// Specify image height in terminal rows (the cell height is rounded down to whole pixels)
+    #[arg(
+        long,
+        value_name = "N",
+        conflicts_with_all = ["fullwidth", "fullheight", "resize", "noresize", "thumbnail"],
+    )]
+    rows: Option<u32>,
+
+    /// Terminal cell size in pixels, for --cols and --rows (detected in main)
+    #[arg(skip = (10, 20))]
+    cell_size: (u32, u32),
+
+    /// Fit images into the box given by both --width and --height
+    #[arg(long, value_enum, default_value_t = FitOption::Stretch)]
+    fit: FitOption,
+
     /// Resize image to fill terminal width
     #[arg(
         short = 'f',
         long,
-        conflicts_with_all = ["width", "height", "fullheight", "resize", "noresize"],
+        conflicts_with_all = ["width", "height", "fullheight", "resize", "noresize", "thumbnail"],
     )]
     fullwidth: bool,
 
@@ -89,7 +207,7 @@ struct Config {
     #[arg(
         short = 'F',
         long,
-        conflicts_with_all = ["width", "height", "fullwidth", "resize", "noresize"],
+        conflicts_with_all = ["width", "height", "fullwidth", "resize", "noresize", "thumbnail"],
     )]
     fullheight: bool,
 
@@ -97,7 +215,7 @@ struct Config {
     #[arg(
         short = 'r',
         long,
-        conflicts_with_all = ["width", "height", "fullwidth", "fullheight", "noresize"],
+        conflicts_with_all = ["width", "height", "fullwidth", "fullheight", "noresize", "thumbnail"],
     )]
     resize: bool,
 
@@ -105,38 +223,300 @@ struct Config {
     #[arg(
         short = 'n',
         long,
-        conflicts_with_all = ["width", "height", "fullwidth", "fullheight", "resize"],
+        conflicts_with_all = ["width", "height", "fullwidth", "fullheight", "resize", "thumbnail"],
     )]
     noresize: bool,
 
+    /// Set the terminal width in pixels instead of detecting it
+    #[arg(long, value_name = "PX", env = "KV_TERM_WIDTH", value_parser = clap::value_parser!(u32).range(1..))]
+    term_width: Option<u32>,
+
+    /// Set the terminal height in pixels instead of detecting it
+    #[arg(long, value_name = "PX", env = "KV_TERM_HEIGHT", value_parser = clap::value_parser!(u32).range(1..))]
+    term_height: Option<u32>,
+
+    /// Keep the given number of terminal rows free below images (interactive terminals only)
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_RESERVE_ROWS)]
+    reserve_rows: u32,
+
+    /// Scale images down so their longest side is at most the given pixels (ignores the terminal size)
+    #[arg(long, value_name = "PX", value_parser = clap::value_parser!(u32).range(1..))]
+    thumbnail: Option<u32>,
+
+    /// Scale images down to at most the given width in pixels, after all other resizing
+    #[arg(long, value_name = "PX", value_parser = clap::value_parser!(u32).range(1..))]
+    max_width: Option<u32>,
+
+    /// Scale images down to at most the given height in pixels, after all other resizing
+    #[arg(long, value_name = "PX", value_parser = clap::value_parser!(u32).range(1..))]
+    max_height: Option<u32>,
+
+    /// Set the resize filter (nearest for pixel art, lanczos3 for photos)
+    #[arg(long, value_enum, default_value_t = FilterOption::Triangle)]
+    filter: FilterOption,
+
+    /// Crop images to the region X,Y,W,H in source pixels before rotating and resizing
+    #[arg(long, value_name = "X,Y,W,H")]
+    crop: Option<String>,
+
+    /// Rotate images clockwise by 90, 180 or 270 degrees (before flipping)
+    #[arg(long, value_name = "DEGREES", value_parser = parse_rotation)]
+    rotate: Option<u32>,
+
+    /// Flip images horizontally
+    #[arg(long)]
+    flip_h: bool,
+
+    /// Flip images vertically
+    #[arg(long)]
+    flip_v: bool,
+
+    /// Brighten (positive) or darken (negative) images
+    #[arg(long, value_name = "VALUE", default_value_t = 0, allow_negative_numbers = true)]
+    brightness: i32,
+
+    /// Increase (positive) or decrease (negative) the contrast of images in percent
+    #[arg(long, value_name = "PERCENT", default_value_t = 0.0, allow_negative_numbers = true)]
+    contrast: f32,
+
+    /// Apply gamma correction to images (above 1 brightens mid tones)
+    #[arg(long, value_name = "GAMMA", default_value_t = 1.0, value_parser = parse_positive)]
+    gamma: f32,
+
+    /// Convert images to grayscale
+    #[arg(long)]
+    grayscale: bool,
+
+    /// Apply a sepia tone to images
+    #[arg(long)]
+    sepia: bool,
+
+    /// Invert the colors of images
+    #[arg(long)]
+    invert: bool,
+
     /// Add background (useful for transparent images)
-    #[arg(short = 'b', long)]
+    #[arg(short = 'b', long, conflicts_with_all = ["checkerboard", "gradient"])]
     background: bool,
 
-    /// Set background color as hex string
+    /// Set background color as hex string, rgb()/rgba() or CSS color name
     #[arg(short = 'c', long, default_value = "#FFFFFF", requires = "background")]
     color: String,
 
-    /// Set transmission mode
-    #[arg(short = 'm', long, value_enum, default_value_t = ModeOption::Png)]
+    /// Draw a checkerboard behind transparent images, optionally with a cell size in pixels
+    #[arg(
+        long,
+        value_name = "SIZE",
+        num_args = 0..=1,
+        default_missing_value = "8",
+        value_parser = clap::value_parser!(u32).range(1..),
+        conflicts_with_all = ["background", "gradient"],
+    )]
+    checkerboard: Option<u32>,
+
+    /// Draw a gradient behind transparent images (e.g. "#000000-white")
+    #[arg(long, value_name = "FROM-TO", conflicts_with_all = ["background", "checkerboard"])]
+    gradient: Option<String>,
+
+    /// Draw the gradient from left to right instead of top to bottom
+    #[arg(long, requires = "gradient")]
+    gradient_horizontal: bool,
+
+    /// Set transmission mode, auto picks the best protocol the terminal supports
+    #[arg(short = 'm', long, value_enum, default_value_t = ModeOption::Auto)]
     mode: ModeOption,
 
-    /// Output to file as png, instead of kitty
+    /// Place images with kitty Unicode placeholders (for embedding in TUIs)
+    #[arg(long, conflicts_with = "output")]
+    placeholder: bool,
+
+    /// Set the kitty image id
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..), conflicts_with = "output")]
+    image_id: Option<u32>,
+
+    /// Place the image at the given column (0-based, in cells) and restore the cursor afterwards
+    #[arg(long, conflicts_with = "output")]
+    col: Option<u32>,
+
+    /// Place the image at the given row (0-based, in cells) and restore the cursor afterwards
+    #[arg(long, conflicts_with = "output")]
+    row: Option<u32>,
+
+    /// Place the image at the cursor without moving it or scrolling (with --col/--row: at that cell)
+    #[arg(long, conflicts_with = "file_output")]
+    in_place: bool,
+
+    /// Set the kitty z-index of the image (negative values draw below text)
+    #[arg(long = "z", value_name = "Z", allow_negative_numbers = true, conflicts_with = "output")]
+    z_index: Option<i32>,
+
+    /// Set the size of each kitty chunk in bytes (a multiple of 4, smaller for tmux)
+    #[arg(long, value_name = "BYTES", default_value_t = KITTY_CHUNK_SIZE, value_parser = parse_chunk_size)]
+    chunk_size: usize,
+
+    /// Wrap kitty graphics in tmux passthrough sequences (default: detected from $TMUX)
+    #[arg(long)]
+    tmux: bool,
+
+    /// Suppress kitty responses: 0 shows all, 1 hides OK responses, 2 (default) also hides errors
+    #[arg(
+        long,
+        value_name = "LEVEL",
+        num_args = 0..=1,
+        default_missing_value = "2",
+        value_parser = clap::value_parser!(u8).range(0..=2),
+        conflicts_with = "output",
+    )]
+    quiet: Option<u8>,
+
+    /// Transmit kitty images inline or through a temporary file (local terminals only)
+    #[arg(long, value_enum, default_value_t = TransferOption::Direct, conflicts_with = "output")]
+    transfer: TransferOption,
+
+    /// Dither images before reducing them to a color palette (sixel)
+    #[arg(long)]
+    dither: bool,
+
+    /// Output to file instead of kitty, as png or by extension as jpg, webp or bmp
     #[arg(short = 'o', long, conflicts_with = "mode")]
     output: Option<String>,
 
-    /// Overwrite existing output file
-    #[arg(short = 'x', long, requires = "output")]
+    /// Set the quality (1-100) of JPEG output files
+    #[arg(long, value_name = "QUALITY", default_value_t = 90, value_parser = clap::value_parser!(u8).range(1..=100))]
+    quality: u8,
+
+    /// Write each input as png into this directory, named by --name-template
+    #[arg(long, value_name = "DIR", conflicts_with_all = ["mode", "montage"])]
+    output_dir: Option<PathBuf>,
+
+    /// Name of the files in --output-dir, with {stem}, {index} (1-based input) and {page} (1-based) placeholders
+    #[arg(long, value_name = "TEMPLATE", default_value = "{stem}.png", requires = "output_dir")]
+    name_template: String,
+
+    /// Write PNG data to stdout instead of terminal graphics (default when stdout is not a tty)
+    #[arg(long, conflicts_with_all = ["output", "output_dir", "mode"])]
+    stdout_png: bool,
+
+    /// Disable syntax highlighting colors (set from $NO_COLOR)
+    #[arg(skip)]
+    no_color: bool,
+
+    /// Detect the mode from the terminal before sending graphics, for --mode auto (set in main)
+    #[arg(skip)]
+    detect_mode: bool,
+
+    /// Overwrite existing output files
+    #[arg(short = 'x', long, requires = "file_output")]
     overwrite: bool,
 
     /// Set input type
     #[arg(short = 'i', long, value_enum, default_value_t = InputTypeOption::Auto)]
     input: InputTypeOption,
 
-    /// Select pages to render (e.g. "1-3,34" or empty for all)
-    #[arg(short = 'P', long, default_value = "1", conflicts_with = "all")]
+    /// Select pages to render (e.g. "1-3,34", "3-" to the end, "-4" from the start, "5-1" reversed, "1-9:2" every other page, or "all" or empty for all)
+    #[arg(short = 'P', long, default_value = "1", conflicts_with = "all", allow_hyphen_values = true)]
     pages: String,
 
+    /// Render pages in the order given by --pages, keeping duplicates, instead of sorted
+    #[arg(long, conflicts_with = "all")]
+    keep_page_order: bool,
+
+    /// Reject page selections of more than N pages
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_MAX_PAGES, value_parser = clap::value_parser!(u32).range(1..))]
+    max_pages: u32,
+
+    /// Render PDF pages at the given resolution instead of the terminal width
+    #[arg(long, value_parser = parse_positive)]
+    dpi: Option<f32>,
+
+    /// Scale the intrinsic SVG size before resizing (e.g. 4 for small icons)
+    #[arg(long, value_name = "SCALE", default_value_t = 1.0, value_parser = parse_positive)]
+    svg_scale: f32,
+
+    /// Set the color used for `currentColor` in SVGs (see --color for formats)
+    #[arg(long, value_name = "COLOR")]
+    svg_color: Option<String>,
+
+    /// Inject a CSS stylesheet into SVGs before rendering
+    #[arg(long, value_name = "PATH")]
+    svg_stylesheet: Option<PathBuf>,
+
+    /// Load fonts for SVG text from a directory (can be repeated)
+    #[arg(long, value_name = "PATH")]
+    font_dir: Vec<PathBuf>,
+
+    /// Load a font file for SVG text (can be repeated)
+    #[arg(long, value_name = "PATH")]
+    font_file: Vec<PathBuf>,
+
+    /// Do not load system fonts for SVG text (only --font-dir/--font-file)
+    #[arg(long)]
+    no_system_fonts: bool,
+
+    /// Arrange PDF pages as "vertical", "horizontal" or "grid:COLS"
+    #[arg(long, value_name = "LAYOUT", default_value = "vertical")]
+    page_layout: String,
+
+    /// Set the gap between combined PDF pages in pixels
+    #[arg(long, value_name = "PX", default_value_t = 10)]
+    page_gap: u32,
+
+    /// Set the color of the gap between PDF pages (see --color for formats)
+    #[arg(long, value_name = "COLOR", default_value = "#808080")]
+    page_gap_color: String,
+
+    /// Send each PDF page as a separate image
+    #[arg(long, conflicts_with_all = ["output", "page_layout", "page_gap", "page_gap_color"])]
+    separate_pages: bool,
+
+    /// Combine all input images into one grid with the given number of columns
+    #[arg(long, value_name = "COLS", value_parser = clap::value_parser!(u32).range(1..))]
+    montage: Option<u32>,
+
+    /// Fit each montage image into a square of the given size in pixels
+    #[arg(long, value_name = "PX", requires = "montage", value_parser = clap::value_parser!(u32).range(1..))]
+    thumb_size: Option<u32>,
+
+    /// Set the padding between montage images in pixels
+    #[arg(long, value_name = "PX", default_value_t = 10)]
+    montage_gap: u32,
+
+    /// Set the browser viewport for HTML rendering (e.g. "1280x720", default: terminal size)
+    #[arg(long, value_name = "WxH")]
+    viewport: Option<String>,
+
+    /// Wait for a CSS selector before capturing HTML (default: "body")
+    #[arg(long, value_name = "SELECTOR")]
+    html_wait: Option<String>,
+
+    /// Wait the given time in milliseconds after loading HTML before capturing
+    #[arg(long, value_name = "MS")]
+    html_delay: Option<u64>,
+
+    /// Set the HTML screenshot format: "png", "jpeg[:QUALITY]" or "webp[:QUALITY]"
+    #[arg(long, value_name = "FORMAT", default_value = "png")]
+    html_capture_format: String,
+
+    /// Set the device pixel ratio for HTML screenshots (e.g. 2 for sharper text)
+    #[arg(long, value_name = "SCALE", default_value_t = 1.0, value_parser = parse_positive)]
+    html_scale: f32,
+
+    /// Set the Chrome/Chromium executable used for HTML rendering
+    #[arg(long, value_name = "PATH", env = "KV_CHROME_PATH")]
+    chrome_path: Option<PathBuf>,
+
+    /// Pass an extra argument to Chrome (can be repeated)
+    #[arg(long, value_name = "ARG", allow_hyphen_values = true)]
+    chrome_arg: Vec<String>,
+
+    /// Run Chrome without its sandbox (e.g. inside Docker)
+    #[arg(long)]
+    no_sandbox: bool,
+
+    /// Preview the video frame at the given offset in seconds (requires the video feature)
+    #[arg(long, value_name = "SECONDS", default_value_t = 1.0, value_parser = parse_non_negative)]
+    video_time: f32,
+
     /// Select all pages
     #[arg(short = 'A', long, conflicts_with = "pages")]
     all: bool,
@@ -145,49 +525,175 @@ struct Config {
     #[arg(short = 'l', long)]
     language: Option<String>,
 
+    /// Show text with this command instead of the built-in highlighter (files as argument, stdin as input)
+    #[arg(long, value_name = "CMD", env = "KV_PAGER")]
+    pager: Option<String>,
+
+    /// Play animations once instead of looping
+    #[arg(long)]
+    no_loop: bool,
+
+    /// Show the progress of slow renders (PDF pages, launching Chrome) on stderr
+    #[arg(long)]
+    progress: bool,
+
     /// Do not add a newline after each input (might mess up the terminal)
     #[arg(short = 'N', long)]
     no_newline: bool,
 
-    /// Do not cache office files
+    /// Do not cache office files or rendered images
     #[arg(short = 'C', long)]
     no_cache: bool,
 
+    /// Cache rendered SVG and PDF images for repeated previews
+    #[arg(long, conflicts_with = "no_cache")]
+    cache: bool,
+
+    /// Set the maximum size of the render cache in megabytes
+    #[arg(long, value_name = "MB", default_value_t = 100)]
+    cache_size: u64,
+
+    /// Load up to N files in parallel (default: number of CPUs)
+    #[arg(short = 'j', long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    jobs: Option<u32>,
+
     /// Print filename before each input
     #[arg(short = 'p', long)]
     printname: bool,
 
-    /// Force tty (ignore stdin check)
+    /// Force tty (ignore stdin and stdout checks)
     #[arg(short = 't', long)]
     tty: bool,
 
     /// Remove all images from terminal
-    #[arg(short = 'R', long, conflicts_with = "plugins")]
+    #[arg(short = 'R', long, conflicts_with_all = ["plugins", "delete_id", "remove_placements", "remove_cursor"])]
     remove: bool,
 
+    /// Remove all image placements from terminal, keeping the image data
+    #[arg(long, conflicts_with_all = ["plugins", "delete_id", "remove_cursor"])]
+    remove_placements: bool,
+
+    /// Remove the images under the cursor from terminal
+    #[arg(long, conflicts_with_all = ["plugins", "delete_id"])]
+    remove_cursor: bool,
+
+    /// Remove the image with the given kitty image id from terminal
+    #[arg(long, value_name = "ID", conflicts_with_all = ["remove", "plugins", "image_id"])]
+    delete_id: Option<u32>,
+
+    /// Render again whenever one of the files changes, until interrupted
+    #[arg(long, requires = "FILES", conflicts_with_all = ["file_output", "info", "probe", "plugins"])]
+    watch: bool,
+
+    /// Print what each input would be rendered as, as a JSON line, without rendering it
+    #[arg(long, conflicts_with_all = ["file_output", "probe", "plugins"])]
+    info: bool,
+
+    /// Print the detected terminal size and graphics support to stderr and exit
+    #[arg(long, conflicts_with = "plugins")]
+    probe: bool,
+
+    /// Print a man page in roff format to stdout and exit
+    #[arg(long)]
+    generate_man: bool,
+
+    /// Read defaults from this TOML file instead of config.toml in the configuration directory
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Ignore the configuration file
+    #[arg(long, conflicts_with = "config")]
+    no_config: bool,
+
     /// Print the plugins configuration file path (will be created if it doesn't exist)
-    #[arg(long, conflicts_with = "remove")]
+    #[arg(long, conflicts_with_all = ["remove", "remove_placements", "remove_cursor", "delete_id"])]
     plugins: bool,
 }
 
+fn parse_chunk_size(s: &str) -> Result<usize, String> {
+    match s.parse::<usize>() {
+        Ok(size) if size > 0 && size.is_multiple_of(4) => Ok(size),
+        _ => Err("must be a positive multiple of 4".to_string()),
+    }
+}
+
+fn parse_rotation(s: &str) -> Result<u32, String> {
+    match s.parse::<u32>() {
+        Ok(degrees @ (90 | 180 | 270)) => Ok(degrees),
+        _ => Err("must be 90, 180 or 270".to_string()),
+    }
+}
+
+fn parse_positive(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(value) if value.is_finite() && value > 0.0 => Ok(value),
+        _ => Err("must be a positive number".to_string()),
+    }
+}
+
+fn parse_non_negative(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(value) if value.is_finite() && value >= 0.0 => Ok(value),
+        _ => Err("must be a non-negative number".to_string()),
+    }
+}
+
 fn run(
     mut writer: impl Write,
     mut err_writer: impl Write,
     mut reader: impl Read,
-    conf: Config,
+    mut conf: Config,
     term_size: (u32, u32),
     is_input_available: bool,
     cache_dir: Option<PathBuf>,
 ) -> Result<i32> {
-    if conf.remove {
-        write!(writer, "\x1b_Ga=d\x1b\\")?;
+    if conf.delete_id.is_some() && (conf.remove || conf.image_id.is_some()) {
+        writeln!(
+            err_writer,
+            "Error: Cannot combine --delete-id with --remove or --image-id"
+        )?;
+        return Ok(1);
+    }
+
+    let remove_mode = match (conf.remove, conf.remove_placements, conf.remove_cursor, conf.delete_id) {
+        (true, _, _, _) => Some(RemoveMode::All),
+        (_, true, _, _) => Some(RemoveMode::Placements),
+        (_, _, true, _) => Some(RemoveMode::Cursor),
+        (_, _, _, Some(id)) => Some(RemoveMode::Id(id)),
+        _ => None,
+    };
+    if let Some(mode) = remove_mode {
+        write_graphics_command(&mut writer, &mode.command(), conf.tmux)?;
+        return Ok(0);
+    }
+
+    if conf.probe {
+        detect_mode(&mut conf);
+        let mode: Mode = conf.mode.clone().into();
+        let caps = detect_capabilities();
+        let (cell_width, cell_height) = get_cell_size();
+        let yes_no = |supported: bool| if supported { "yes" } else { "no" };
+        writeln!(err_writer, "terminal size: {}x{} px", term_size.0, term_size.1)?;
+        writeln!(err_writer, "cell size: {}x{} px", cell_width, cell_height)?;
+        writeln!(err_writer, "kitty graphics: {}", yes_no(caps.kitty))?;
+        writeln!(err_writer, "sixel: {}", yes_no(caps.sixel))?;
+        writeln!(err_writer, "iterm2: {}", yes_no(caps.iterm2))?;
+        writeln!(err_writer, "mode: {}", format!("{:?}", mode).to_lowercase())?;
         return Ok(0);
     }
 
     // If -t is passed, we ignore stdin even if input is available
     let use_stdin = is_input_available && !conf.tty;
 
-    if conf.output.is_some() && !use_stdin && conf.files.len() > 1 {
+    match resolve_files(&conf.files, conf.recursive) {
+        Ok(files) => conf.files = files,
+        Err(e) => {
+            writeln!(err_writer, "Error: {:#}", e)?;
+            return Ok(1);
+        }
+    }
+
+    if conf.output.is_some() && !use_stdin && conf.files.len() > 1 && conf.montage.is_none() {
         writeln!(
             err_writer,
             "Error: Cannot specify multiple files with --output"
@@ -195,7 +701,7 @@ fn run(
         return Ok(1);
     }
 
-    let page_indices = if conf.all {
+    let pages = if conf.all {
         if !use_stdin && conf.files.len() > 1 {
             writeln!(
                 err_writer,
@@ -206,7 +712,7 @@ fn run(
         None
     } else if let Ok(pages) = parse_pages(&conf.pages) {
         // if pages != [0], disallow multiple files
-        if !use_stdin && conf.files.len() > 1 && pages != Some(vec![0]) {
+        if !use_stdin && conf.files.len() > 1 && pages != Some(vec![PageRange::from(0)]) {
             writeln!(
                 err_writer,
                 "Error: Cannot specify multiple files with non-default --pages option"
@@ -219,7 +725,17 @@ fn run(
         return Ok(1);
     };
 
-    let resize_mode = if conf.noresize {
+    // whole cells; the cell size is rounded down, so the image never spills into another cell
+    let cells_width = conf.cols.map(|cols| cols.checked_mul(conf.cell_size.0));
+    let cells_height = conf.rows.map(|rows| rows.checked_mul(conf.cell_size.1));
+    if cells_width == Some(None) || cells_height == Some(None) {
+        writeln!(err_writer, "Error: --cols or --rows is too large for the cell size")?;
+        return Ok(1);
+    }
+
+    let resize_mode = if let Some(max_side) = conf.thumbnail {
+        ResizeMode::Thumbnail(max_side)
+    } else if conf.noresize {
         ResizeMode::Original
     } else if conf.resize {
         ResizeMode::FitTerminal
@@ -227,10 +743,16 @@ fn run(
         ResizeMode::FitWidth
     } else if conf.fullheight {
         ResizeMode::FitHeight
-    } else if conf.width.is_some() || conf.height.is_some() {
+    } else if conf.width.is_some() || conf.height.is_some() || conf.cols.is_some() || conf.rows.is_some() {
         ResizeMode::Manual {
-            width: conf.width,
-            height: conf.height,
+            width: conf.width.or(cells_width.flatten()),
+            height: conf.height.or(cells_height.flatten()),
+            fit: match conf.fit.clone().into() {
+                // covering never distorts, all other fits are padded to the box
+                Fit::Cover => Fit::Cover,
+                _ if conf.keep_aspect => Fit::Letterbox,
+                fit => fit,
+            },
         }
     } else {
         ResizeMode::ClipTerminal
@@ -244,19 +766,125 @@ fn run(
         CacheMode::Default
     };
 
-    let background_color = if conf.background {
-        Some(parse_color(&conf.color)?)
+    let background = if conf.background {
+        Some(Background::Color(parse_color(&conf.color)?))
+    } else if let Some(gradient) = &conf.gradient {
+        let (from, to) = parse_gradient(gradient)?;
+        Some(Background::Gradient {
+            from,
+            to,
+            vertical: !conf.gradient_horizontal,
+        })
     } else {
-        None
+        conf.checkerboard.map(Background::Checkerboard)
     };
 
     let ctx = KvContext {
         input_type: conf.input.clone().into(),
         resize_mode,
+        resize_filter: conf.filter.clone().into(),
+        max_width: conf.max_width,
+        max_height: conf.max_height,
+        crop: conf.crop.as_deref().map(parse_crop).transpose()?,
+        rotate: conf.rotate.unwrap_or(0),
+        flip_h: conf.flip_h,
+        flip_v: conf.flip_v,
+        brightness: conf.brightness,
+        contrast: conf.contrast,
+        gamma: conf.gamma,
+        grayscale: conf.grayscale,
+        sepia: conf.sepia,
+        invert: conf.invert,
         term_size,
-        page_indices,
+        pages,
+        keep_page_order: conf.keep_page_order,
+        max_pages: conf.max_pages,
+        pdf_dpi: conf.dpi,
+        svg_scale: conf.svg_scale,
+        svg_color: conf.svg_color.as_deref().map(parse_color).transpose()?,
+        svg_stylesheet: conf
+            .svg_stylesheet
+            .as_ref()
+            .map(|path| {
+                std::fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read stylesheet {}", path.display()))
+            })
+            .transpose()?,
+        font_dirs: conf.font_dir.clone(),
+        font_files: conf.font_file.clone(),
+        no_system_fonts: conf.no_system_fonts,
+        page_layout: parse_page_layout(&conf.page_layout)?,
+        viewport: conf.viewport.as_deref().map(parse_viewport).transpose()?,
+        html_wait: conf.html_wait.clone(),
+        html_delay: conf.html_delay,
+        html_capture_format: parse_capture_format(&conf.html_capture_format)?,
+        html_scale: conf.html_scale,
+        chrome_path: conf.chrome_path.clone(),
+        chrome_args: conf.chrome_arg.clone(),
+        no_sandbox: conf.no_sandbox,
+        page_gap: conf.page_gap,
+        page_gap_color: parse_color(&conf.page_gap_color)?,
+        separate_pages: conf.separate_pages,
         cache_mode,
-        background_color,
+        render_cache: conf.cache,
+        cache_size: conf.cache_size * 1024 * 1024,
+        video_time: conf.video_time,
+        progress: conf.progress,
+        background,
+    };
+
+    if conf.info && (use_stdin || !conf.files.is_empty()) {
+        // without querying the terminal, which only happens when sending graphics
+        let mode = match (conf.detect_mode, mode_from_environment()) {
+            (true, None) => "auto".to_string(),
+            (true, Some(mode)) => format!("{:?}", mode).to_lowercase(),
+            (false, _) => format!("{:?}", Mode::from(conf.mode.clone())).to_lowercase(),
+        };
+        let infos: Vec<(String, Result<InputInfo>)> = if use_stdin {
+            let mut data = Vec::new();
+            reader.read_to_end(&mut data)?;
+            vec![("stdin".to_string(), inspect_data(&ctx, &data, ""))]
+        } else {
+            conf.files.iter().map(|path| (path.display().to_string(), inspect_file(&ctx, path))).collect()
+        };
+        let mut exit_code = 0;
+        for (name, info) in infos {
+            match info {
+                Ok(info) => {
+                    let line = InfoLine { name: &name, info, mode: &mode };
+                    writeln!(writer, "{}", serde_json::to_string(&line)?)?;
+                }
+                Err(e) => {
+                    writeln!(err_writer, "Error loading {}: {}", name, e)?;
+                    exit_code = 1;
+                }
+            }
+        }
+        return Ok(exit_code);
+    }
+
+    detect_mode(&mut conf);
+    let colored = !conf.no_color && !conf.stdout_png;
+
+    let send_opts = SendOptions {
+        // stdout is written like an output file
+        // --output-dir writes each file on its own, see write_output_files
+        output: if conf.stdout_png { Some("-".to_string()) } else { conf.output.clone() },
+        mode: conf.mode.clone().into(),
+        looping: !conf.no_loop,
+        image_id: conf.image_id,
+        placeholder: conf.placeholder,
+        cell_size: if conf.placeholder || conf.mode == ModeOption::Blocks { get_cell_size() } else { (10, 20) },
+        col: conf.col,
+        row: conf.row,
+        in_place: conf.in_place,
+        z_index: conf.z_index,
+        chunk_size: conf.chunk_size,
+        tmux: conf.tmux,
+        quiet: conf.quiet,
+        transfer: conf.transfer.clone().into(),
+        dither: conf.dither,
+        quality: conf.quality,
     };
 
     if use_stdin {
@@ -264,53 +892,65 @@ fn run(
             writeln!(err_writer, "stdin")?;
         }
 
+        // detect the format from a prefix, only other inputs than text are read completely
         let mut data = Vec::new();
-        reader.read_to_end(&mut data)?;
-
-        match load_data(&ctx, &data, "") {
-            Ok(LoadResult::Image(img)) => {
-                send_image(
-                    &mut writer,
-                    img,
-                    conf.output.clone(),
-                    conf.mode.clone().into(),
-                )?;
-            }
-            Ok(LoadResult::Data(data)) => {
-                pretty_print(
-                    &mut writer,
-                    PrinterInput::Data(data),
-                    conf.language.as_deref(),
-                    !conf.no_newline,
-                )?;
-            }
-            Err(e) => {
-                writeln!(err_writer, "Error decoding stdin: {}", e)?;
-                return Ok(1);
+        (&mut reader).take(STDIN_PREFIX_SIZE as u64).read_to_end(&mut data)?;
+        if conf.output_dir.is_none() && is_text_prefix(&ctx, &data) {
+            let input = Cursor::new(data).chain(&mut reader);
+            show_text(&mut writer, &conf, PrinterInput::Reader(Box::new(input)), colored)?;
+        } else {
+            reader.read_to_end(&mut data)?;
+
+            match load_data(&ctx, &data, "") {
+                Ok(result) if conf.output_dir.is_some() => {
+                    if let Err(e) = write_output_files(&conf, "stdin", 1, result, &send_opts) {
+                        writeln!(err_writer, "Error writing stdin: {}", e)?;
+                        return Ok(1);
+                    }
+                }
+                Ok(LoadResult::Image(img)) => {
+                    send_image(&mut writer, img, &send_opts)?;
+                }
+                Ok(LoadResult::Png { image, data }) => {
+                    send_png(&mut writer, image, &data, &send_opts)?;
+                }
+                Ok(LoadResult::Animation(frames)) => {
+                    send_animation(&mut writer, frames, &send_opts)?;
+                }
+                Ok(LoadResult::Pages(pages)) => {
+                    send_pages(&mut writer, pages, &send_opts)?;
+                }
+                Ok(LoadResult::Data(data)) => {
+                    show_text(&mut writer, &conf, PrinterInput::Data(data), colored)?;
+                }
+                Err(e) => {
+                    writeln!(err_writer, "Error decoding stdin: {}", e)?;
+                    return Ok(1);
+                }
             }
         }
-    } else if !conf.files.is_empty() {
+    } else if let (Some(cols), false) = (conf.montage, conf.files.is_empty()) {
         let mut exit_code = 0;
-        for path in &conf.files {
+        let mut thumb_ctx = ctx.clone();
+        if let Some(size) = conf.thumb_size {
+            thumb_ctx.resize_mode = ResizeMode::FitTerminal;
+            thumb_ctx.term_size = (size, size);
+        }
+        let mut images = Vec::new();
+        // decode at most --jobs files at once, like the other files
+        let results = conf.files.chunks(job_count(&conf)).flat_map(|batch| load_files(&thumb_ctx, batch));
+        for (path, result) in conf.files.iter().zip(results) {
             if conf.printname {
                 writeln!(err_writer, "{}", path.display())?;
             }
-            match load_file(&ctx, path) {
-                Ok(LoadResult::Image(img)) => {
-                    send_image(
-                        &mut writer,
-                        img,
-                        conf.output.clone(),
-                        conf.mode.clone().into(),
-                    )?;
-                }
+            match result {
+                Ok(LoadResult::Image(img)) | Ok(LoadResult::Png { image: img, .. }) => images.push(img),
+                // the first frame or page stands for the file
+                Ok(LoadResult::Animation(frames)) => images.extend(frames.into_iter().next().map(|f| f.image)),
+                Ok(LoadResult::Pages(pages)) => images.extend(pages.into_iter().next()),
                 Ok(LoadResult::Data(_)) => {
-                    pretty_print(
-                        &mut writer,
-                        PrinterInput::File(path.clone()),
-                        conf.language.as_deref(),
-                        !conf.no_newline,
-                    )?;
+                    writeln!(err_writer, "Error loading {}: Not an image", path.display())?;
+                    exit_code = 1;
                 }
                 Err(e) => {
                     writeln!(err_writer, "Error loading {}: {}", path.display(), e)?;
@@ -318,6 +958,52 @@ fn run(
                 }
             }
         }
+        if !images.is_empty() {
+            let montage = render_montage(&ctx, images, cols, conf.montage_gap)?;
+            send_image(&mut writer, montage, &send_opts)?;
+        }
+        return Ok(exit_code);
+    } else if !conf.files.is_empty() {
+        let mut exit_code = 0;
+        let jobs = job_count(&conf);
+        // load each batch in parallel, but write the results in order
+        for (batch_index, batch) in conf.files.chunks(jobs).enumerate() {
+            let results = load_files(&ctx, batch);
+            for (i, (path, result)) in batch.iter().zip(results).enumerate() {
+                if conf.printname {
+                    writeln!(err_writer, "{}", path.display())?;
+                }
+                match result {
+                    Ok(result) if conf.output_dir.is_some() => {
+                        let stem = path.file_stem().map_or_else(|| "output".into(), |stem| stem.to_string_lossy());
+                        let index = batch_index * jobs + i + 1;
+                        if let Err(e) = write_output_files(&conf, &stem, index, result, &send_opts) {
+                            writeln!(err_writer, "Error writing {}: {}", path.display(), e)?;
+                            exit_code = 1;
+                        }
+                    }
+                    Ok(LoadResult::Image(img)) => {
+                        send_image(&mut writer, img, &send_opts)?;
+                    }
+                    Ok(LoadResult::Png { image, data }) => {
+                        send_png(&mut writer, image, &data, &send_opts)?;
+                    }
+                    Ok(LoadResult::Animation(frames)) => {
+                        send_animation(&mut writer, frames, &send_opts)?;
+                    }
+                    Ok(LoadResult::Pages(pages)) => {
+                        send_pages(&mut writer, pages, &send_opts)?;
+                    }
+                    Ok(LoadResult::Data(_)) => {
+                        show_text(&mut writer, &conf, PrinterInput::File(path.clone()), colored)?;
+                    }
+                    Err(e) => {
+                        writeln!(err_writer, "Error loading {}: {}", path.display(), e)?;
+                        exit_code = 1;
+                    }
+                }
+            }
+        }
         return Ok(exit_code);
     } else {
         writeln!(
@@ -330,11 +1016,394 @@ fn run(
     Ok(0)
 }
 
+/// The mode for known terminals, from the environment alone.
+fn mode_from_environment() -> Option<Mode> {
+    let term = std::env::var("TERM").ok();
+    let term_program = std::env::var("TERM_PROGRAM").ok();
+    let kitty_window = std::env::var_os("KITTY_WINDOW_ID").is_some();
+    mode_from_env(term.as_deref(), term_program.as_deref(), kitty_window)
+}
+
+/// Resolves --mode auto if main left it to be detected, querying terminals the environment does not tell.
+fn detect_mode(conf: &mut Config) {
+    if std::mem::take(&mut conf.detect_mode) && conf.mode == ModeOption::Auto {
+        conf.mode = mode_from_environment()
+            .unwrap_or_else(|| mode_from_capabilities(detect_capabilities()))
+            .into();
+    }
+}
+
+/// Number of files loaded in parallel, --jobs or the available parallelism.
+fn job_count(conf: &Config) -> usize {
+    conf.jobs.map_or_else(
+        || std::thread::available_parallelism().map_or(1, |n| n.get()),
+        |jobs| jobs as usize,
+    )
+}
+
+/// Replaces patterns with wildcards by the matching paths, sorted, other paths are kept.
+#[cfg(feature = "glob")]
+fn expand_globs(files: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut expanded = Vec::new();
+    for path in files {
+        let pattern = path.to_string_lossy();
+        // existing files might have wildcard characters in their name, URLs query strings
+        if path.exists() || pattern.contains("://") || !pattern.contains(['*', '?', '[']) {
+            expanded.push(path.clone());
+            continue;
+        }
+        let matches = glob::glob(&pattern)
+            .with_context(|| format!("Invalid pattern {}", pattern))?
+            .collect::<Result<Vec<_>, _>>()?;
+        if matches.is_empty() {
+            anyhow::bail!("No files match {}", pattern);
+        }
+        expanded.extend(matches);
+    }
+    Ok(expanded)
+}
+
+/// Expands glob patterns and, with --recursive, the supported files in directories.
+fn resolve_files(files: &[PathBuf], recursive: bool) -> Result<Vec<PathBuf>> {
+    #[cfg(feature = "glob")]
+    let files = &expand_globs(files)?;
+    if !recursive {
+        return Ok(files.to_vec());
+    }
+    let mut resolved = Vec::new();
+    for path in files {
+        if path.is_dir() {
+            resolved.extend(walk_dir(path).with_context(|| format!("Failed to read {}", path.display()))?);
+        } else {
+            resolved.push(path.clone());
+        }
+    }
+    Ok(resolved)
+}
+
+/// Whether the watcher event changes one of the (canonical) files.
+fn is_change(event: &notify::Event, files: &[PathBuf]) -> bool {
+    matches!(event.kind, notify::EventKind::Create(_) | notify::EventKind::Modify(_))
+        && event.paths.iter().any(|path| files.contains(path))
+}
+
+/// Renders the files again after each change, replacing the previous images.
+fn watch(mut conf: Config, term_size: (u32, u32)) -> Result<i32> {
+    use notify::Watcher;
+
+    // query the terminal once, not for every render
+    detect_mode(&mut conf);
+    // render exactly the watched files, directories and patterns are resolved once
+    conf.files = resolve_files(&conf.files, conf.recursive)?;
+    conf.recursive = false;
+    let files = conf
+        .files
+        .iter()
+        .map(|path| path.canonicalize().with_context(|| format!("Failed to watch {}", path.display())))
+        .collect::<Result<Vec<_>>>()?;
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    // editors often replace files instead of writing them, so watch the directories
+    let mut dirs: Vec<&Path> = files.iter().filter_map(|path| path.parent()).collect();
+    dirs.sort();
+    dirs.dedup();
+    for dir in dirs {
+        watcher.watch(dir, notify::RecursiveMode::NonRecursive)?;
+    }
+
+    let mode: Mode = conf.mode.clone().into();
+    loop {
+        if !is_change(&rx.recv()??, &files) {
+            continue;
+        }
+        while rx.recv_timeout(std::time::Duration::from_millis(WATCH_DEBOUNCE_MS)).is_ok() {}
+
+        let mut stdout = io::stdout();
+        if mode.is_kitty() {
+            write_graphics_command(&mut stdout, &RemoveMode::All.command(), conf.tmux)?;
+        }
+        write!(stdout, "\x1b[H\x1b[2J")?;
+        stdout.flush()?;
+        // keep watching, the next change might fix the error
+        if let Err(e) = run(stdout, io::stderr(), io::empty(), conf.clone(), term_size, false, None) {
+            writeln!(io::stderr(), "Error: {:#}", e)?;
+        }
+    }
+}
+
+/// Writes the man page generated from the flag definitions.
+fn write_man_page(writer: &mut dyn Write) -> Result<()> {
+    clap_mangen::Man::new(Config::command()).render(writer)?;
+    Ok(())
+}
+
+/// Defaults from the configuration file, keys are named like the flags.
+/// Flags and environment variables take precedence.
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+struct FileConfig {
+    mode: Option<ModeOption>,
+    transfer: Option<TransferOption>,
+    filter: Option<FilterOption>,
+    fit: Option<FitOption>,
+    reserve_rows: Option<u32>,
+    quality: Option<u8>,
+    dither: Option<bool>,
+    tmux: Option<bool>,
+    background: Option<bool>,
+    color: Option<String>,
+    checkerboard: Option<u32>,
+    language: Option<String>,
+    pager: Option<String>,
+    chrome_path: Option<PathBuf>,
+    no_sandbox: Option<bool>,
+    jobs: Option<u32>,
+    cache_size: Option<u64>,
+}
+
+impl FileConfig {
+    fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        toml::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    /// Sets the values of `conf` that were not given as flags or environment variables.
+    fn apply(self, conf: &mut Config, matches: &ArgMatches) -> Result<()> {
+        let unset = |id: &str| {
+            !matches!(matches.value_source(id), Some(ValueSource::CommandLine | ValueSource::EnvVariable))
+        };
+
+        // the mode conflicts with output files
+        if let Some(mode) = self.mode {
+            if unset("mode") && conf.output.is_none() && conf.output_dir.is_none() && !conf.stdout_png {
+                conf.mode = mode;
+            }
+        }
+        if let Some(transfer) = self.transfer {
+            if unset("transfer") && conf.output.is_none() {
+                conf.transfer = transfer;
+            }
+        }
+        if let Some(filter) = self.filter {
+            if unset("filter") {
+                conf.filter = filter;
+            }
+        }
+        if let Some(fit) = self.fit {
+            if unset("fit") {
+                conf.fit = fit;
+            }
+        }
+        if let Some(reserve_rows) = self.reserve_rows {
+            if unset("reserve_rows") {
+                conf.reserve_rows = reserve_rows;
+            }
+        }
+        if let Some(quality) = self.quality {
+            if !(1..=100).contains(&quality) {
+                anyhow::bail!("quality must be between 1 and 100");
+            }
+            if unset("quality") {
+                conf.quality = quality;
+            }
+        }
+        if let Some(dither) = self.dither {
+            if unset("dither") {
+                conf.dither = dither;
+            }
+        }
+        if let Some(tmux) = self.tmux {
+            if unset("tmux") {
+                conf.tmux = tmux;
+            }
+        }
+
+        // one background at most, a background flag replaces the one from the file
+        if self.background == Some(true) && self.checkerboard.is_some() {
+            anyhow::bail!("background and checkerboard cannot be combined");
+        }
+        if unset("background") && unset("checkerboard") && unset("gradient") {
+            if let Some(background) = self.background {
+                conf.background = background;
+            }
+            if let Some(checkerboard) = self.checkerboard {
+                if checkerboard == 0 {
+                    anyhow::bail!("checkerboard must be at least 1");
+                }
+                conf.checkerboard = Some(checkerboard);
+            }
+        }
+
+        // the color also applies to a background from the command line
+        if let Some(color) = self.color {
+            if unset("color") {
+                conf.color = color;
+            }
+        }
+
+        if let Some(language) = self.language {
+            if unset("language") {
+                conf.language = Some(language);
+            }
+        }
+        if let Some(pager) = self.pager {
+            if unset("pager") {
+                conf.pager = Some(pager);
+            }
+        }
+        if let Some(chrome_path) = self.chrome_path {
+            if unset("chrome_path") {
+                conf.chrome_path = Some(chrome_path);
+            }
+        }
+        if let Some(no_sandbox) = self.no_sandbox {
+            if unset("no_sandbox") {
+                conf.no_sandbox = no_sandbox;
+            }
+        }
+        if let Some(jobs) = self.jobs {
+            if jobs == 0 {
+                anyhow::bail!("jobs must be at least 1");
+            }
+            if unset("jobs") {
+                conf.jobs = Some(jobs);
+            }
+        }
+        if let Some(cache_size) = self.cache_size {
+            if unset("cache_size") {
+                conf.cache_size = cache_size;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parses the flags and fills in the defaults from the configuration file.
+fn parse_config(matches: &ArgMatches) -> Result<Config> {
+    let mut conf = Config::from_arg_matches(matches)?;
+    if conf.no_config {
+        return Ok(conf);
+    }
+    // a missing file is only an error if it was given explicitly
+    let path = match &conf.config {
+        Some(path) => path.clone(),
+        None => get_defaults_path(),
+    };
+    if conf.config.is_some() || path.exists() {
+        FileConfig::load(&path)?
+            .apply(&mut conf, matches)
+            .with_context(|| format!("Invalid configuration in {}", path.display()))?;
+    }
+    Ok(conf)
+}
+
+/// One line of --info output.
+#[derive(serde::Serialize)]
+struct InfoLine<'a> {
+    name: &'a str,
+    #[serde(flatten)]
+    info: InputInfo,
+    mode: &'a str,
+}
+
+/// Shows text with the configured pager, or highlighted on the writer.
+fn show_text(writer: &mut impl Write, conf: &Config, input: PrinterInput, colored: bool) -> Result<()> {
+    if let Some(pager) = &conf.pager {
+        writer.flush()?;
+        return run_pager(writer, pager, input);
+    }
+    pretty_print(writer, input, conf.language.as_deref(), !conf.no_newline, colored)
+}
+
+/// Fills the --name-template placeholders for one output file.
+fn output_name(template: &str, stem: &str, index: usize, page: usize) -> String {
+    template
+        .replace("{stem}", stem)
+        .replace("{index}", &index.to_string())
+        .replace("{page}", &page.to_string())
+}
+
+/// Writes the rendered image of one input into --output-dir, one file per page.
+fn write_output_files(
+    conf: &Config,
+    stem: &str,
+    index: usize,
+    result: LoadResult,
+    send_opts: &SendOptions,
+) -> Result<()> {
+    let dir = conf.output_dir.as_deref().context("No output directory")?;
+    // the original PNG bytes are kept where possible
+    let images: Vec<(DynamicImage, Option<Vec<u8>>)> = match result {
+        LoadResult::Image(img) => vec![(img, None)],
+        LoadResult::Png { image, data } => vec![(image, Some(data))],
+        LoadResult::Animation(frames) => frames.into_iter().take(1).map(|f| (f.image, None)).collect(),
+        LoadResult::Pages(pages) => pages.into_iter().map(|page| (page, None)).collect(),
+        LoadResult::Data(_) => anyhow::bail!("Not an image"),
+    };
+    if images.len() > 1 && !conf.name_template.contains("{page}") {
+        anyhow::bail!("Name template needs a {{page}} placeholder for multiple pages");
+    }
+
+    for (page, (image, png)) in images.into_iter().enumerate() {
+        let path = dir.join(output_name(&conf.name_template, stem, index, page + 1));
+        let path = path.to_string_lossy().into_owned();
+        let (mut writer, temp_output) = prepare_writer(Some(path.clone()), conf.overwrite)?;
+        let opts = SendOptions { output: Some(path), ..send_opts.clone() };
+        match png {
+            Some(png) => send_png(&mut writer, image, &png, &opts)?,
+            None => send_image(&mut writer, image, &opts)?,
+        }
+        writer.flush()?;
+        drop(writer);
+        if let Some((tempfile, final_path)) = temp_output {
+            persist_output(tempfile, &final_path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Loads the files on one thread each, returning the results in input order.
+fn load_files(ctx: &KvContext, paths: &[PathBuf]) -> Vec<Result<LoadResult>> {
+    if paths.len() == 1 {
+        return vec![load_file(ctx, &paths[0])];
+    }
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = paths
+            .iter()
+            .map(|path| scope.spawn(move || load_file(ctx, path)))
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|_| Err(anyhow::anyhow!("Loading thread panicked")))
+            })
+            .collect()
+    })
+}
+
+/// Moves the written temp file to its final path, synced to disk first,
+/// and with the permissions of the file it replaces.
+fn persist_output(tempfile: NamedTempFile, final_path: &Path) -> Result<()> {
+    tempfile.as_file().sync_all().context("Failed to sync output file")?;
+    if let Ok(metadata) = std::fs::metadata(final_path) {
+        std::fs::set_permissions(tempfile.path(), metadata.permissions())
+            .context("Failed to copy output file permissions")?;
+    }
+    tempfile
+        .persist(final_path)
+        .with_context(|| format!("Failed to write {}", final_path.display()))?;
+    Ok(())
+}
+
 fn prepare_writer(
     output: Option<String>,
     overwrite: bool,
 ) -> Result<(Box<dyn Write>, TempAndFinalOption)> {
     match output {
+        // "-" writes the encoded image to stdout, like --stdout-png
+        Some(path_str) if path_str == "-" => Ok((Box::new(BufWriter::new(io::stdout())), None)),
         Some(path_str) => {
             let path = PathBuf::from(path_str);
 
@@ -375,21 +1444,76 @@ fn prepare_writer(
     }
 }
 
-fn main() -> Result<()> {
-    let conf = Config::parse();
+/// Returns the terminal size in pixels, detecting only what is not set by the user.
+fn resolve_term_size(conf: &Config) -> (u32, u32) {
+    match (conf.term_width, conf.term_height) {
+        (Some(width), Some(height)) => (width, height),
+        (width, height) => {
+            // positioned images and non-interactive output do not push the prompt down
+            let interactive = conf.output.is_none()
+                && conf.output_dir.is_none()
+                && !conf.stdout_png
+                && atty::is(atty::Stream::Stdout);
+            let positioned = conf.col.is_some() || conf.row.is_some() || conf.in_place;
+            let reserve_rows = if interactive && !positioned { conf.reserve_rows } else { 0 };
+            let (detected_width, detected_height) = get_term_size(reserve_rows);
+            (width.unwrap_or(detected_width), height.unwrap_or(detected_height))
+        }
+    }
+}
 
+fn main() -> Result<()> {
+    let matches = Config::command().get_matches();
+    // before reading the configuration file, which cannot change the man page
+    if matches.get_flag("generate_man") {
+        write_man_page(&mut io::stdout())?;
+        return Ok(());
+    }
+    let mut conf = parse_config(&matches)?;
     if conf.plugins {
         open_config()?;
         return Ok(());
     }
+    conf.tmux |= std::env::var_os("TMUX").is_some();
+    conf.no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+    if conf.cols.is_some() || conf.rows.is_some() {
+        conf.cell_size = get_cell_size();
+    }
+    // escape sequences are useless in a pipe or file, unless kitty options ask for them
+    let kitty_options = conf.placeholder || conf.image_id.is_some() || conf.z_index.is_some();
+    let wants_graphics = conf.mode != ModeOption::Auto
+        || kitty_options
+        || conf.col.is_some()
+        || conf.row.is_some()
+        || conf.in_place;
+    let writes_files = conf.output.is_some() || conf.output_dir.is_some();
+    if !conf.tty && !wants_graphics && !writes_files && atty::isnt(atty::Stream::Stdout) {
+        conf.stdout_png = true;
+    }
+    if conf.mode == ModeOption::Auto {
+        if kitty_options || conf.stdout_png || writes_files {
+            conf.mode = ModeOption::Png;
+        } else {
+            // querying the terminal switches it to raw mode, so run only does it to send graphics
+            conf.detect_mode = true;
+        }
+    }
+    // a remote terminal cannot read our temporary files
+    if std::env::var_os("SSH_CONNECTION").is_some() || std::env::var_os("SSH_TTY").is_some() {
+        conf.transfer = TransferOption::Direct;
+    }
+
+    // progress messages would end up in logs
+    conf.progress &= atty::is(atty::Stream::Stderr);
 
-    let term_size = get_term_size();
+    let term_size = resolve_term_size(&conf);
 
     // Detect TTY status
     let is_input_available = atty::isnt(atty::Stream::Stdin);
 
     let (writer, temp_output) = prepare_writer(conf.output.clone(), conf.overwrite)?;
 
+    let watch_conf = conf.watch.then(|| conf.clone());
     let code = run(
         writer,
         io::stderr(),
@@ -398,12 +1522,21 @@ fn main() -> Result<()> {
         term_size,
         is_input_available,
         None,
-    )?;
+    );
+    // keep watching after failed renders, the next change might fix them
+    let code = match watch_conf {
+        Some(conf) if code.is_ok() => watch(conf, term_size),
+        _ => code,
+    };
+
+    // also on errors, so no Chrome process is left behind
+    close_browser();
+    let code = code?;
 
     // Commit temp file only on success
     if let Some((tempfile, final_path)) = temp_output {
         if code == 0 {
-            tempfile.persist(final_path)?;
+            persist_output(tempfile, &final_path)?;
         }
     }
 
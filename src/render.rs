@@ -1,17 +1,19 @@
 use anyhow::{Context, Result};
-use image::{DynamicImage,GenericImageView, GenericImage, Rgba, RgbaImage};
+use image::{DynamicImage, Frame, GenericImageView, GenericImage, Rgba, RgbaImage};
 use std::process::{Command,Stdio};
-use image::imageops::FilterType;
+use std::hash::Hash;
 use std::io::Write;
+use std::sync::{Mutex, OnceLock};
 
-use crate::{calculate_dimensions, ResizeMode, CacheMode,kv_project_dirs, Plugin};
+use crate::{calculate_dimensions, clamp_dimensions, resolve_pages, Background, Fit, ResizeMode, CacheMode,kv_project_dirs, Plugin};
 
 use pdfium_render::prelude::{PdfRenderConfig, Pdfium};
 
-use crate::{InputType, KvContext};
+use crate::{AnimationFrame, CaptureFormat, InputType, KvContext, PageLayout};
 use base64::{engine::general_purpose, Engine as _};
 use std::path::PathBuf;
 
+use headless_chrome::protocol::cdp::Emulation;
 use headless_chrome::protocol::cdp::Page::CaptureScreenshotFormatOption;
 use headless_chrome::{Browser, LaunchOptions};
 
@@ -21,89 +23,454 @@ use sha2::{Digest, Sha256};
 mod tests_render;
 
 
+/// Light and dark cell colors of the transparency checkerboard.
+const CHECKERBOARD_LIGHT: Rgba<u8> = Rgba([204, 204, 204, 255]);
+const CHECKERBOARD_DARK: Rgba<u8> = Rgba([153, 153, 153, 255]);
+
+/// Blends a single source pixel over a background pixel ("source-over" compositing).
+///
+/// Both pixels use straight (non-premultiplied) alpha, and so does the result:
+/// `a = sa + da * (1 - sa)` and `c = (sc * sa + dc * da * (1 - sa)) / a`.
+/// For an opaque background this reduces to `sc * sa + dc * (1 - sa)` with alpha 255.
+fn blend_pixel(dst: &mut Rgba<u8>, src: &Rgba<u8>) {
+    let alpha = src[3] as u32;
+    if alpha == 255 {
+        *dst = *src;
+    } else if alpha > 0 {
+        // manual blending, all terms are scaled by 255 * 255
+        let inv_alpha = 255 - alpha;
+        let bg_alpha = dst[3] as u32;
+        let out_alpha = alpha * 255 + bg_alpha * inv_alpha;
+
+        let blend = |s: u8, d: u8| -> u8 {
+            ((s as u32 * alpha * 255 + d as u32 * bg_alpha * inv_alpha) / out_alpha) as u8
+        };
+
+        *dst = Rgba([
+            blend(src[0], dst[0]),
+            blend(src[1], dst[1]),
+            blend(src[2], dst[2]),
+            ((out_alpha + 127) / 255) as u8,
+        ]);
+    }
+}
+
+#[cfg(any(not(feature = "parallel"), test))]
+fn blend_onto_serial(bg: &mut RgbaImage, rgba: &RgbaImage) {
+    for (dst, src) in bg.pixels_mut().zip(rgba.pixels()) {
+        blend_pixel(dst, src);
+    }
+}
+
+#[cfg(feature = "parallel")]
+fn blend_onto_parallel(bg: &mut RgbaImage, rgba: &RgbaImage) {
+    use image::Pixel;
+    use rayon::prelude::*;
+
+    let row_len = bg.width() as usize * 4;
+    if row_len == 0 {
+        return;
+    }
+
+    // process rows in parallel
+    bg.par_chunks_mut(row_len)
+        .zip(rgba.par_chunks(row_len))
+        .for_each(|(dst_row, src_row)| {
+            for (dst, src) in dst_row.chunks_exact_mut(4).zip(src_row.chunks_exact(4)) {
+                blend_pixel(Rgba::from_slice_mut(dst), Rgba::from_slice(src));
+            }
+        });
+}
+
+/// Blends the image over the given background buffer (same size).
+fn blend_onto(bg: &mut RgbaImage, img: &DynamicImage) {
+    let rgba = img.to_rgba8();
+
+    #[cfg(feature = "parallel")]
+    blend_onto_parallel(bg, &rgba);
+
+    #[cfg(not(feature = "parallel"))]
+    blend_onto_serial(bg, &rgba);
+}
+
 pub fn add_background(img: &DynamicImage, color: &Rgba<u8>) -> DynamicImage {
     let mut bg = RgbaImage::from_pixel(img.width(), img.height(), *color);
-    let rgba = img.to_rgba8();
+    blend_onto(&mut bg, img);
+    DynamicImage::ImageRgba8(bg)
+}
 
-    for (dst, src) in bg.pixels_mut().zip(rgba.pixels()) {
-        let alpha = src[3] as u32;
-        if alpha == 255 {
-            *dst = *src;
-        } else if alpha > 0 {
-             // manual blending: src * alpha + bg * (1 - alpha)
-            let inv_alpha = 255 - alpha;
-            let bg_r = dst[0] as u32;
-            let bg_g = dst[1] as u32;
-            let bg_b = dst[2] as u32;
-
-            let r = (src[0] as u32 * alpha + bg_r * inv_alpha) / 255;
-            let g = (src[1] as u32 * alpha + bg_g * inv_alpha) / 255;
-            let b = (src[2] as u32 * alpha + bg_b * inv_alpha) / 255;
-
-            *dst = Rgba([r as u8, g as u8, b as u8, 255]);
+/// Composites the image over a checkerboard of `light` and `dark` cells of `cell_size` pixels.
+pub fn add_checkerboard(
+    img: &DynamicImage,
+    cell_size: u32,
+    light: &Rgba<u8>,
+    dark: &Rgba<u8>,
+) -> DynamicImage {
+    let cell_size = cell_size.max(1);
+    let mut bg = RgbaImage::from_fn(img.width(), img.height(), |x, y| {
+        if (x / cell_size + y / cell_size).is_multiple_of(2) { *light } else { *dark }
+    });
+    blend_onto(&mut bg, img);
+    DynamicImage::ImageRgba8(bg)
+}
+
+/// Composites the image over a linear gradient from `from` to `to`,
+/// interpolated per row if `vertical`, per column otherwise.
+pub fn add_gradient_background(
+    img: &DynamicImage,
+    from: &Rgba<u8>,
+    to: &Rgba<u8>,
+    vertical: bool,
+) -> DynamicImage {
+    let (w, h) = img.dimensions();
+    let steps = if vertical { h } else { w };
+    let lerp = |i: u32| -> Rgba<u8> {
+        let t = if steps > 1 { i as f32 / (steps - 1) as f32 } else { 0.0 };
+        let mut color = [0u8; 4];
+        for (c, (a, b)) in color.iter_mut().zip(from.0.iter().zip(to.0.iter())) {
+            *c = (*a as f32 + (*b as f32 - *a as f32) * t).round() as u8;
         }
-    }
+        Rgba(color)
+    };
+    let colors: Vec<Rgba<u8>> = (0..steps).map(lerp).collect();
 
+    let mut bg = RgbaImage::from_fn(w, h, |x, y| {
+        colors[if vertical { y } else { x } as usize]
+    });
+    blend_onto(&mut bg, img);
     DynamicImage::ImageRgba8(bg)
 }
 
+/// Draws the configured background behind the image.
+pub fn apply_background(img: &DynamicImage, background: &Background) -> DynamicImage {
+    match background {
+        Background::Color(color) => add_background(img, color),
+        Background::Checkerboard(cell_size) => {
+            add_checkerboard(img, *cell_size, &CHECKERBOARD_LIGHT, &CHECKERBOARD_DARK)
+        }
+        Background::Gradient { from, to, vertical } => {
+            add_gradient_background(img, from, to, *vertical)
+        }
+    }
+}
+
+/// Rotates clockwise by `ctx.rotate` degrees, then flips.
+fn orient_image(ctx: &KvContext, img: DynamicImage) -> DynamicImage {
+    let mut img = match ctx.rotate {
+        90 => img.rotate90(),
+        180 => img.rotate180(),
+        270 => img.rotate270(),
+        _ => img,
+    };
+    if ctx.flip_h {
+        img = img.fliph();
+    }
+    if ctx.flip_v {
+        img = img.flipv();
+    }
+    img
+}
+
+/// Cuts the `ctx.crop` region out of the image, which must lie within its bounds.
+fn crop_image(ctx: &KvContext, img: DynamicImage) -> Result<DynamicImage> {
+    let Some((x, y, width, height)) = ctx.crop else {
+        return Ok(img);
+    };
+    let (img_width, img_height) = img.dimensions();
+    if x as u64 + width as u64 > img_width as u64 || y as u64 + height as u64 > img_height as u64 {
+        anyhow::bail!(
+            "Crop region {},{},{},{} is outside the {}x{} image",
+            x, y, width, height, img_width, img_height
+        );
+    }
+    Ok(img.crop_imm(x, y, width, height))
+}
+
 pub fn render_image(ctx: &KvContext, img: DynamicImage) -> Result<DynamicImage> {
+    // crop in source pixels, then orient, so the size is calculated for the rotated image
+    let img = orient_image(ctx, crop_image(ctx, img)?);
     let (w, h) = calculate_dimensions(img.dimensions(), ctx.resize_mode, ctx.term_size);
+    let (w, h) = clamp_dimensions((w, h), ctx.max_width, ctx.max_height);
     let mut final_img = img;
 
     if w != 0 && h != 0 && (w != final_img.width() || h != final_img.height()) {
-        final_img = final_img.resize_exact(w, h, FilterType::Triangle);
+        final_img = final_img.resize_exact(w, h, ctx.resize_filter);
     }
 
-    if let Some(color) = ctx.background_color {
-        final_img = add_background(&final_img, &color);
+    // --fit cover: crop the centered box out of the covering image
+    if let ResizeMode::Manual { width: Some(box_w), height: Some(box_h), fit: Fit::Cover } = ctx.resize_mode {
+        if final_img.width() > box_w || final_img.height() > box_h {
+            let x = final_img.width().saturating_sub(box_w) / 2;
+            let y = final_img.height().saturating_sub(box_h) / 2;
+            final_img = final_img.crop_imm(x, y, box_w, box_h);
+        }
+    }
+
+    // --keep-aspect: center the contained image in the box
+    if let ResizeMode::Manual { width: Some(box_w), height: Some(box_h), fit: Fit::Letterbox } = ctx.resize_mode {
+        let (box_w, box_h) = clamp_dimensions((box_w, box_h), ctx.max_width, ctx.max_height);
+        if final_img.width() < box_w || final_img.height() < box_h {
+            let mut boxed = RgbaImage::new(box_w, box_h);
+            let x = box_w.saturating_sub(final_img.width()) / 2;
+            let y = box_h.saturating_sub(final_img.height()) / 2;
+            image::imageops::overlay(&mut boxed, &final_img.to_rgba8(), x as i64, y as i64);
+            final_img = DynamicImage::ImageRgba8(boxed);
+        }
     }
-    Ok(final_img)
+
+    Ok(finish_image(ctx, final_img))
 }
 
-pub fn render_svg(ctx: &KvContext, data: &[u8]) -> Result<DynamicImage> {
+/// Applies the steps after resizing: background compositing, adjustments, then color filters.
+fn finish_image(ctx: &KvContext, img: DynamicImage) -> DynamicImage {
+    let img = match &ctx.background {
+        Some(background) => apply_background(&img, background),
+        None => img,
+    };
+    apply_color_filters(ctx, apply_adjustments(ctx, img))
+}
+
+/// Applies --brightness, --contrast and --gamma, in this order, keeping the alpha channel.
+fn apply_adjustments(ctx: &KvContext, mut img: DynamicImage) -> DynamicImage {
+    if ctx.brightness != 0 {
+        img = img.brighten(ctx.brightness);
+    }
+    if ctx.contrast != 0.0 {
+        // same curve as `imageops::contrast`, which would also change the alpha channel
+        let factor = ((100.0 + ctx.contrast) / 100.0).powi(2);
+        img = map_color_channels(img, |c| (c - 0.5) * factor + 0.5);
+    }
+    if ctx.gamma != 1.0 {
+        // values above 1 brighten the mid tones
+        img = map_color_channels(img, |c| c.powf(1.0 / ctx.gamma));
+    }
+    img
+}
+
+/// Maps the color channels (scaled to 0..1) through `f`, clamping the results.
+fn map_color_channels(img: DynamicImage, f: impl Fn(f32) -> f32) -> DynamicImage {
+    let lut: Vec<u8> = (0..=255)
+        .map(|c| (f(c as f32 / 255.0) * 255.0).round().clamp(0.0, 255.0) as u8)
+        .collect();
+    let mut rgba = img.to_rgba8();
+    for pixel in rgba.pixels_mut() {
+        for c in &mut pixel.0[..3] {
+            *c = lut[*c as usize];
+        }
+    }
+    DynamicImage::ImageRgba8(rgba)
+}
+
+/// Applies --grayscale, --sepia and --invert, in this order, keeping the alpha channel.
+fn apply_color_filters(ctx: &KvContext, img: DynamicImage) -> DynamicImage {
+    if !ctx.grayscale && !ctx.sepia && !ctx.invert {
+        return img;
+    }
+    let mut img = if ctx.grayscale {
+        DynamicImage::ImageRgba8(img.grayscale().to_rgba8())
+    } else {
+        DynamicImage::ImageRgba8(img.to_rgba8())
+    };
+    if ctx.sepia {
+        for pixel in img.as_mut_rgba8().expect("converted to rgba8").pixels_mut() {
+            let [r, g, b, a] = pixel.0.map(|c| c as f32);
+            let mix = |fr: f32, fg: f32, fb: f32| (r * fr + g * fg + b * fb).round().min(255.0) as u8;
+            *pixel = Rgba([
+                mix(0.393, 0.769, 0.189),
+                mix(0.349, 0.686, 0.168),
+                mix(0.272, 0.534, 0.131),
+                a as u8,
+            ]);
+        }
+    }
+    if ctx.invert {
+        img.invert();
+    }
+    img
+}
+
+/// Minimum frame delay; shorter delays are treated like browsers do (100 ms).
+const MIN_FRAME_DELAY_MS: u32 = 20;
+const DEFAULT_FRAME_DELAY_MS: u32 = 100;
+
+/// Renders each decoded animation frame like a still image, keeping its delay.
+pub fn render_frames(ctx: &KvContext, frames: Vec<Frame>) -> Result<Vec<AnimationFrame>> {
+    frames
+        .into_iter()
+        .map(|frame| {
+            let (numer, denom) = frame.delay().numer_denom_ms();
+            let delay_ms = numer / denom.max(1);
+            let delay_ms = if delay_ms < MIN_FRAME_DELAY_MS { DEFAULT_FRAME_DELAY_MS } else { delay_ms };
+            let image = render_image(ctx, DynamicImage::ImageRgba8(frame.into_buffer()))?;
+            Ok(AnimationFrame { image, delay_ms })
+        })
+        .collect()
+}
+
+/// Combines --svg-color and --svg-stylesheet into the CSS injected into the SVG.
+/// The stylesheet comes last, so its rules can override the color.
+fn svg_style_sheet(ctx: &KvContext) -> Option<String> {
+    let color = ctx
+        .svg_color
+        .map(|Rgba([r, g, b, _])| format!("svg {{ color: #{:02x}{:02x}{:02x}; }}\n", r, g, b));
+    match (color, &ctx.svg_stylesheet) {
+        (None, None) => None,
+        (color, style_sheet) => Some(color.unwrap_or_default() + style_sheet.as_deref().unwrap_or_default()),
+    }
+}
+
+/// Removes the width and height attributes from the root `<svg>` element.
+fn without_svg_size(data: &[u8]) -> Option<String> {
+    let text = std::str::from_utf8(data).ok()?;
+    let doc = usvg::roxmltree::Document::parse(text).ok()?;
+    let mut ranges: Vec<_> = doc
+        .root_element()
+        .attributes()
+        .filter(|attr| attr.namespace().is_none() && matches!(attr.name(), "width" | "height"))
+        .map(|attr| attr.range())
+        .collect();
+    ranges.sort_by_key(|range| std::cmp::Reverse(range.start));
+
+    let mut text = text.to_string();
+    for range in ranges {
+        text.replace_range(range, "");
+    }
+    Some(text)
+}
+
+/// Parses an SVG with the fonts and stylesheet from the context.
+/// SVGs with a zero width or height fall back to their viewBox size or a default canvas.
+fn parse_svg(ctx: &KvContext, data: &[u8]) -> Result<usvg::Tree> {
     let mut fontdb = usvg::fontdb::Database::new();
-    fontdb.load_system_fonts();
+    if !ctx.no_system_fonts {
+        fontdb.load_system_fonts();
+    }
+    for dir in &ctx.font_dirs {
+        fontdb.load_fonts_dir(dir);
+    }
+    for file in &ctx.font_files {
+        fontdb
+            .load_font_file(file)
+            .with_context(|| format!("Failed to load font {}", file.display()))?;
+    }
 
-    let opt = usvg::Options {
+    let mut opt = usvg::Options {
         fontdb: std::sync::Arc::new(fontdb),
+        style_sheet: svg_style_sheet(ctx),
         ..Default::default()
     };
 
-    let tree = usvg::Tree::from_data(data, &opt).context("Failed to parse SVG")?;
-    let size = tree.size().to_int_size();
-    
-    let (new_w, new_h) = calculate_dimensions(
-        (size.width(), size.height()),
-        ctx.resize_mode,
-        ctx.term_size
-    );
+    match usvg::Tree::from_data(data, &opt) {
+        Err(usvg::Error::InvalidSize) => {
+            opt.default_size = usvg::Size::from_wh(512.0, 512.0).unwrap();
+            without_svg_size(data)
+                .and_then(|text| usvg::Tree::from_str(&text, &opt).ok())
+                .context("Failed to parse SVG: invalid size")
+        }
+        tree => tree.context("Failed to parse SVG"),
+    }
+}
+
+/// Size of the SVG after --svg-scale, rounded up, so the content is never cut off.
+fn scaled_svg_size(ctx: &KvContext, tree: &usvg::Tree) -> (u32, u32) {
+    let size = tree.size();
+    (
+        ((size.width() * ctx.svg_scale).ceil() as u32).max(1),
+        ((size.height() * ctx.svg_scale).ceil() as u32).max(1),
+    )
+}
+
+pub fn render_svg(ctx: &KvContext, data: &[u8]) -> Result<DynamicImage> {
+    let tree = parse_svg(ctx, data)?;
+    let size = tree.size();
+    // rasterize as if the SVG was drawn at the scaled size, so small icons stay sharp
+    let scaled_size = scaled_svg_size(ctx, &tree);
+
+    // cropping and orienting work on source pixels, so rasterize at the scaled size first
+    // so does fitting into a box with cropping or padding, which render_image takes care of
+    let needs_source = ctx.crop.is_some()
+        || ctx.rotate != 0
+        || ctx.flip_h
+        || ctx.flip_v
+        || matches!(ctx.resize_mode, ResizeMode::Manual { fit: Fit::Cover | Fit::Letterbox, .. });
+    let (new_w, new_h) = if needs_source {
+        scaled_size
+    } else {
+        let dims = calculate_dimensions(scaled_size, ctx.resize_mode, ctx.term_size);
+        clamp_dimensions(dims, ctx.max_width, ctx.max_height)
+    };
 
     let mut pixmap = tiny_skia::Pixmap::new(new_w, new_h)
         .ok_or_else(|| anyhow::anyhow!("Failed to create pixmap"))?;
 
-    if let Some(color) = ctx.background_color {
-        pixmap.fill(tiny_skia::Color::from_rgba8(color[0], color[1], color[2], color[3]));
-    }
-
-    let scale_x = new_w as f32 / size.width() as f32;
-    let scale_y = new_h as f32 / size.height() as f32;
+    let scale_x = new_w as f32 / size.width();
+    let scale_y = new_h as f32 / size.height();
     let transform = tiny_skia::Transform::from_scale(scale_x, scale_y);
 
     resvg::render(&tree, transform, &mut pixmap.as_mut());
 
-    let buffer = RgbaImage::from_raw(new_w, new_h, pixmap.data().to_vec())
+    // tiny-skia stores premultiplied alpha, convert to straight alpha for blending
+    let data = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|p| {
+            let c = p.demultiply();
+            [c.red(), c.green(), c.blue(), c.alpha()]
+        })
+        .collect();
+    let buffer = RgbaImage::from_raw(new_w, new_h, data)
         .ok_or_else(|| anyhow::anyhow!("Failed buffer conversion"))?;
 
-    Ok(DynamicImage::ImageRgba8(buffer))
+    let img = DynamicImage::ImageRgba8(buffer);
+    if needs_source {
+        return render_image(ctx, img);
+    }
+    Ok(finish_image(ctx, img))
+}
+
+/// Intrinsic SVG size after --svg-scale, as rasterized by `render_svg`, without rendering.
+pub(crate) fn svg_size(ctx: &KvContext, data: &[u8]) -> Result<(u32, u32)> {
+    Ok(scaled_svg_size(ctx, &parse_svg(ctx, data)?))
+}
+
+/// Page count and the size of the first page in points, without rendering.
+pub(crate) fn pdf_size(data: &[u8]) -> Result<(u32, (u32, u32))> {
+    let document = get_pdfium()?.load_pdf_from_byte_slice(data, None)?;
+    let pages = document.pages();
+    let first = pages.get(0).context("No pages found in PDF")?;
+    let size = (first.width().value.round() as u32, first.height().value.round() as u32);
+    Ok((pages.len() as u32, size))
 }
 
 pub fn render_pdf(ctx: &KvContext, data: &[u8]) -> Result<DynamicImage> {
-    let width = match ctx.resize_mode {
-        ResizeMode::Manual { width: Some(w), .. } => w,
-        ResizeMode::FitWidth | ResizeMode::FitTerminal => ctx.term_size.0,
-        _ => if ctx.term_size.0 > 0 { ctx.term_size.0 } else { 800 },
-    };
+    let images = render_pdf_bitmaps(ctx, data)?;
+    let combined = combine_pages(images, ctx.page_layout, ctx.page_gap, ctx.page_gap_color)?;
+    render_image(ctx, DynamicImage::ImageRgba8(combined))
+}
+
+/// Renders the selected PDF pages as separate images instead of combining them.
+pub fn render_pdf_pages(ctx: &KvContext, data: &[u8]) -> Result<Vec<DynamicImage>> {
+    render_pdf_bitmaps(ctx, data)?
+        .into_iter()
+        .map(|img| render_image(ctx, DynamicImage::ImageRgba8(img)))
+        .collect()
+}
+
+static PDFIUM: OnceLock<Pdfium> = OnceLock::new();
+
+/// Binds the pdfium library on first use and reuses the binding for the rest of the process.
+fn get_pdfium() -> Result<&'static Pdfium> {
+    if let Some(pdfium) = PDFIUM.get() {
+        return Ok(pdfium);
+    }
+
+    // an explicitly configured location takes precedence over the search paths
+    if let Some(path) = std::env::var_os("KV_PDFIUM_PATH") {
+        let path = PathBuf::from(path);
+        let bindings = Pdfium::bind_to_library(Pdfium::pdfium_platform_library_name_at_path(&path))
+            .with_context(|| format!("Failed to bind pdfium from KV_PDFIUM_PATH ({})", path.display()))?;
+        return Ok(PDFIUM.get_or_init(|| Pdfium::new(bindings)));
+    }
 
     let pdfium = Pdfium::new(
         Pdfium::bind_to_library(Pdfium::pdfium_platform_library_name_at_path("./"))
@@ -112,48 +479,127 @@ pub fn render_pdf(ctx: &KvContext, data: &[u8]) -> Result<DynamicImage> {
             .or_else(|_| Pdfium::bind_to_library(Pdfium::pdfium_platform_library_name_at_path("/usr/local/lib")))
             .or_else(|_| Pdfium::bind_to_system_library())?,
     );
+    Ok(PDFIUM.get_or_init(|| pdfium))
+}
 
-    let config = PdfRenderConfig::new()
-        .set_target_width(width.try_into().unwrap_or(800))
-        .render_form_data(true);
+fn render_pdf_bitmaps(ctx: &KvContext, data: &[u8]) -> Result<Vec<RgbaImage>> {
+    let width = match ctx.resize_mode {
+        ResizeMode::Manual { width: Some(w), .. } => w,
+        ResizeMode::FitWidth | ResizeMode::FitTerminal => ctx.term_size.0,
+        _ => if ctx.term_size.0 > 0 { ctx.term_size.0 } else { 800 },
+    };
+
+    let pdfium = get_pdfium()?;
+
+    let config = match ctx.pdf_dpi {
+        // PDF page sizes are given in points (1/72 inch)
+        Some(dpi) => PdfRenderConfig::new().scale_page_by_factor(dpi / 72.0),
+        None => PdfRenderConfig::new().set_target_width(width.try_into().unwrap_or(800)),
+    }
+    .render_form_data(true);
         
     let document = pdfium.load_pdf_from_byte_slice(data, None)?;
     let pages = document.pages();
-    let n_pages = pages.len();
+    let n_pages = pages.len() as u32;
     
-    let selected_indices = if let Some(page_indices) = &ctx.page_indices {
-        if page_indices.iter().any(|&i| i >= n_pages) {
-            anyhow::bail!("Page index out of range (must be <= {})", n_pages);
-        }
-        page_indices.clone()
+    let selected_indices = if let Some(pages) = &ctx.pages {
+        resolve_pages(pages, n_pages, ctx.keep_page_order, ctx.max_pages)?
     } else {
         (0..n_pages).collect()
     };
 
-    let mut images: Vec<RgbaImage> = Vec::with_capacity(selected_indices.len());
-    for page_index in selected_indices {
-        let page = pages.get(page_index).context(format!("Failed to get page {}", page_index))?;
-        let bitmap = page.render_with_config(&config)?;
-        images.push(bitmap.as_image().to_rgba8());
-    }
+    let n_selected = selected_indices.len();
+    let images: Result<Vec<RgbaImage>> = selected_indices
+        .into_iter()
+        .enumerate()
+        .map(|(i, page_index)| {
+            report_progress(ctx, &format!("Rendering page {}/{}", i + 1, n_selected));
+            // resolved indices are below the page count, which pdfium keeps in a u16
+            let page = pages.get(page_index as u16).context(format!("Failed to get page {}", page_index))?;
+            let bitmap = page.render_with_config(&config)?;
+            Ok(bitmap.as_image().to_rgba8())
+        })
+        .collect();
+    // also on errors, so they do not end up after the message
+    clear_progress(ctx);
+    let images = images?;
 
     if images.is_empty() {
         anyhow::bail!("No pages found in PDF");
     }
 
-    let max_width = images.iter().map(|img| img.width()).max().unwrap_or(0);
-    let total_height = images.iter().map(|img| img.height()).sum::<u32>();
-    
-    let mut combined = RgbaImage::new(max_width, total_height);
-    let mut current_y = 0;
-    for img in images {
-        combined.copy_from(&img, 0, current_y)?;
-        current_y += img.height();
+    Ok(images)
+}
+
+/// Composites rendered pages into a single image according to the layout,
+/// separating adjacent pages by `gap` pixels of `gap_color`.
+/// Horizontal and grid layouts center each page in a cell padded with white.
+pub fn combine_pages(
+    images: Vec<RgbaImage>,
+    layout: PageLayout,
+    gap: u32,
+    gap_color: Rgba<u8>,
+) -> Result<RgbaImage> {
+    let cols = match layout {
+        PageLayout::Vertical => {
+            let max_width = images.iter().map(|img| img.width()).max().unwrap_or(0);
+            let total_height = images.iter().map(|img| img.height()).sum::<u32>()
+                + gap * (images.len() as u32).saturating_sub(1);
+
+            let mut combined = RgbaImage::new(max_width, total_height);
+            let mut current_y = 0;
+            for (i, img) in images.iter().enumerate() {
+                if i > 0 && gap > 0 {
+                    fill_rect(&mut combined, 0, current_y, max_width, gap, gap_color);
+                    current_y += gap;
+                }
+                combined.copy_from(img, 0, current_y)?;
+                current_y += img.height();
+            }
+            return Ok(combined);
+        }
+        PageLayout::Horizontal => images.len().max(1),
+        PageLayout::Grid(cols) => (cols as usize).clamp(1, images.len().max(1)),
+    };
+    let rows = images.len().div_ceil(cols);
+
+    let cell_width = images.iter().map(|img| img.width()).max().unwrap_or(0);
+    let cell_height = images.iter().map(|img| img.height()).max().unwrap_or(0);
+
+    let mut combined = RgbaImage::from_pixel(
+        cell_width * cols as u32 + gap * (cols as u32 - 1),
+        cell_height * rows as u32 + gap * (rows as u32).saturating_sub(1),
+        gap_color,
+    );
+    for i in 0..rows * cols {
+        let cell_x = (i % cols) as u32 * (cell_width + gap);
+        let cell_y = (i / cols) as u32 * (cell_height + gap);
+        fill_rect(&mut combined, cell_x, cell_y, cell_width, cell_height, Rgba([255, 255, 255, 255]));
+        if let Some(img) = images.get(i) {
+            let x = cell_x + (cell_width - img.width()) / 2;
+            let y = cell_y + (cell_height - img.height()) / 2;
+            combined.copy_from(img, x, y)?;
+        }
     }
-    
+    Ok(combined)
+}
+
+/// Arranges images in a grid with `cols` columns and `gap` pixels of white padding,
+/// then resizes the montage like a single image.
+pub fn render_montage(ctx: &KvContext, images: Vec<DynamicImage>, cols: u32, gap: u32) -> Result<DynamicImage> {
+    let images = images.into_iter().map(|img| img.to_rgba8()).collect();
+    let combined = combine_pages(images, PageLayout::Grid(cols), gap, Rgba([255, 255, 255, 255]))?;
     render_image(ctx, DynamicImage::ImageRgba8(combined))
 }
 
+fn fill_rect(img: &mut RgbaImage, x: u32, y: u32, width: u32, height: u32, color: Rgba<u8>) {
+    for py in y..y + height {
+        for px in x..x + width {
+            img.put_pixel(px, py, color);
+        }
+    }
+}
+
 fn is_url(s: &[u8]) -> bool {
     s.starts_with(b"http://") || s.starts_with(b"https://") || s.starts_with(b"file://")
 }
@@ -166,6 +612,57 @@ pub fn is_html(ctx: &KvContext, extension: &str, s: &[u8]) -> bool {
     ctx.input_type == InputType::Html || extension == "html" || extension == "htm" || is_url(s)
 }
 
+/// Shows a progress message on stderr with --progress, replacing the previous one.
+fn report_progress(ctx: &KvContext, message: &str) {
+    if ctx.progress {
+        eprint!("\r\x1b[2K{}", message);
+    }
+}
+
+/// Removes the last progress message.
+fn clear_progress(ctx: &KvContext) {
+    if ctx.progress {
+        eprint!("\r\x1b[2K");
+    }
+}
+
+static BROWSER: Mutex<Option<Browser>> = Mutex::new(None);
+
+/// Launches headless Chrome on first use and reuses it for all HTML inputs of the process.
+fn get_browser(ctx: &KvContext) -> Result<Browser> {
+    let mut browser = BROWSER.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(browser) = browser.as_ref() {
+        return Ok(browser.clone());
+    }
+
+    let user_data_dir = kv_project_dirs().data_dir.join("chromium");
+    std::fs::create_dir_all(&user_data_dir)?;
+    // the full page capture grows the height to fit the content
+    let window_size = ctx.viewport.unwrap_or(ctx.term_size);
+    report_progress(ctx, "Launching browser...");
+    let launched = Browser::new(LaunchOptions {
+        headless: true,
+        path: ctx.chrome_path.clone(),
+        user_data_dir: Some(user_data_dir),
+        window_size: Some(window_size),
+        sandbox: !ctx.no_sandbox,
+        args: ctx.chrome_args.iter().map(std::ffi::OsStr::new).collect(),
+        ..Default::default()
+    })
+    .map_err(|e| match &ctx.chrome_path {
+        Some(path) => anyhow::anyhow!("Failed to launch Chrome at {}: {}", path.display(), e),
+        None => anyhow::anyhow!("Chrome/Chromium not found; set --chrome-path ({})", e),
+    });
+    clear_progress(ctx);
+    Ok(browser.insert(launched?).clone())
+}
+
+/// Shuts down the shared Chrome instance, if one was launched.
+/// Statics are never dropped, so this must be called before the process exits.
+pub fn close_browser() {
+    BROWSER.lock().unwrap_or_else(|e| e.into_inner()).take();
+}
+
 pub fn render_html_chrome(ctx: &KvContext, data: &[u8]) -> Result<DynamicImage> {
     let data_str = std::str::from_utf8(data)?;
     let url: String = if is_url_str(data_str) {
@@ -183,19 +680,39 @@ pub fn render_html_chrome(ctx: &KvContext, data: &[u8]) -> Result<DynamicImage>
         }
     };
 
-    let user_data_dir = kv_project_dirs().data_dir.join("chromium");
-    std::fs::create_dir_all(&user_data_dir)?;
-    let browser = Browser::new(LaunchOptions {
-        headless: true,
-        path: None,
-        user_data_dir: Some(user_data_dir),
-        ..Default::default()
-    })?;
-    let tab = browser.new_tab()?;
+    let tab = get_browser(ctx)?.new_tab()?;
+    if ctx.html_scale != 1.0 {
+        // a width and height of 0 keep the window size
+        tab.call_method(Emulation::SetDeviceMetricsOverride {
+            width: 0,
+            height: 0,
+            device_scale_factor: ctx.html_scale.into(),
+            mobile: false,
+            scale: None,
+            screen_width: None,
+            screen_height: None,
+            position_x: None,
+            position_y: None,
+            dont_set_visible_size: None,
+            screen_orientation: None,
+            viewport: None,
+            display_feature: None,
+            device_posture: None,
+        })?;
+    }
     tab.navigate_to(&url)?;
-    tab.wait_for_element("body")?;
-    let png_data = tab.capture_screenshot(CaptureScreenshotFormatOption::Png, None, None, true)?;
-    let img = image::load_from_memory(&png_data)?;
+    tab.wait_for_element(ctx.html_wait.as_deref().unwrap_or("body"))?;
+    if let Some(delay) = ctx.html_delay {
+        std::thread::sleep(std::time::Duration::from_millis(delay));
+    }
+    let (format, quality) = match ctx.html_capture_format {
+        CaptureFormat::Png => (CaptureScreenshotFormatOption::Png, None),
+        CaptureFormat::Jpeg(quality) => (CaptureScreenshotFormatOption::Jpeg, quality),
+        CaptureFormat::Webp(quality) => (CaptureScreenshotFormatOption::Webp, quality),
+    };
+    let screenshot = tab.capture_screenshot(format, quality, None, true)?;
+    tab.close(true)?;
+    let img = image::load_from_memory(&screenshot)?;
     render_image(ctx, img)
 }
 
@@ -204,11 +721,161 @@ mod win;
 #[cfg(target_os = "windows")]
 use win as sys;
 
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+const OLE_MAGIC: &[u8] = &[0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1];
+
+/// Checks that zip based office documents are zip archives containing their main entry,
+/// to report corrupt or mislabeled files before handing them to LibreOffice.
+fn check_office_data(data: &[u8], extension: &str) -> Result<()> {
+    let entry = match extension {
+        "docx" => "word/document.xml",
+        "xlsx" => "xl/workbook.xml",
+        "pptx" => "ppt/presentation.xml",
+        "odt" | "ods" | "odp" => "content.xml",
+        _ => return Ok(()),
+    };
+    let format = extension.to_uppercase();
+
+    if !data.starts_with(ZIP_MAGIC) {
+        if data.starts_with(OLE_MAGIC) && !extension.starts_with("od") {
+            anyhow::bail!(
+                "Not a valid {}: this is a legacy binary .{} file, rename or convert it",
+                format,
+                &extension[..3]
+            );
+        }
+        anyhow::bail!("Not a valid {}: not a zip archive", format);
+    }
+    // entry names are stored uncompressed in the zip headers
+    if !data.windows(entry.len()).any(|window| window == entry.as_bytes()) {
+        anyhow::bail!("Not a valid {}: missing {}", format, entry);
+    }
+    Ok(())
+}
+
+/// Returns the directory of cached renders, or `None` if render caching is off.
+fn render_cache_dir(ctx: &KvContext) -> Option<PathBuf> {
+    if !ctx.render_cache {
+        return None;
+    }
+    match &ctx.cache_mode {
+        CacheMode::Disabled => None,
+        CacheMode::Default => Some(kv_project_dirs().cache_dir.join("renders")),
+        CacheMode::Custom(path) => Some(path.join("renders")),
+    }
+}
+
+/// Feeds `Hash` implementations into SHA-256, which, unlike `DefaultHasher`, is the same in every run.
+struct Sha256Hasher(Sha256);
+
+impl std::hash::Hasher for Sha256Hasher {
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        let digest = self.0.clone().finalize();
+        u64::from_le_bytes(digest[..8].try_into().unwrap())
+    }
+}
+
+/// Hashes the contents of the font files in the directory and its subdirectories.
+/// Unreadable entries are skipped, like fontdb does when loading the directory.
+fn hash_font_dir(hasher: &mut Sha256Hasher, dir: &std::path::Path) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let mut paths: Vec<PathBuf> = entries.filter_map(|entry| entry.ok().map(|entry| entry.path())).collect();
+    paths.sort();
+    for path in paths {
+        if path.is_dir() {
+            hash_font_dir(hasher, &path);
+        } else if let Ok(font) = std::fs::read(&path) {
+            font.hash(hasher);
+        }
+    }
+}
+
+/// Hex digest of the data and the options that change rendered SVGs and PDFs.
+/// Output, cache and browser settings are left out, so they do not invalidate cached renders.
+fn render_cache_key(ctx: &KvContext, data: &[u8]) -> String {
+    let mut hasher = Sha256Hasher(Sha256::new());
+    data.hash(&mut hasher);
+    (ctx.resize_mode, ctx.resize_filter, ctx.max_width, ctx.max_height, ctx.term_size).hash(&mut hasher);
+    (ctx.crop, ctx.rotate, ctx.flip_h, ctx.flip_v).hash(&mut hasher);
+    (ctx.brightness, ctx.contrast.to_bits(), ctx.gamma.to_bits()).hash(&mut hasher);
+    (ctx.grayscale, ctx.sepia, ctx.invert, ctx.background).hash(&mut hasher);
+    (&ctx.pages, ctx.keep_page_order, ctx.max_pages, ctx.pdf_dpi.map(f32::to_bits)).hash(&mut hasher);
+    (ctx.page_layout, ctx.page_gap, ctx.page_gap_color, ctx.separate_pages).hash(&mut hasher);
+    (ctx.svg_scale.to_bits(), ctx.svg_color, &ctx.svg_stylesheet, ctx.no_system_fonts).hash(&mut hasher);
+    // fonts by content, so editing a font renders the text again
+    for file in &ctx.font_files {
+        std::fs::read(file).ok().hash(&mut hasher);
+    }
+    for dir in &ctx.font_dirs {
+        hash_font_dir(&mut hasher, dir);
+    }
+    hex::encode(hasher.0.finalize())
+}
+
+/// Renders the data, reusing a previous result for the same data and render options.
+/// Cache failures never fail the render.
+pub fn render_cached(
+    ctx: &KvContext,
+    data: &[u8],
+    render: impl FnOnce(&KvContext, &[u8]) -> Result<DynamicImage>,
+) -> Result<DynamicImage> {
+    let Some(cache_dir) = render_cache_dir(ctx) else {
+        return render(ctx, data);
+    };
+
+    let cache_path = cache_dir.join(format!("{}.png", render_cache_key(ctx, data)));
+
+    if let Ok(img) = image::open(&cache_path) {
+        // mark as recently used for pruning
+        if let Ok(file) = std::fs::File::options().write(true).open(&cache_path) {
+            let _ = file.set_modified(std::time::SystemTime::now());
+        }
+        return Ok(img);
+    }
+
+    let img = render(ctx, data)?;
+    if std::fs::create_dir_all(&cache_dir).is_ok() && img.save(&cache_path).is_ok() {
+        let _ = prune_cache(&cache_dir, ctx.cache_size);
+    }
+    Ok(img)
+}
+
+/// Deletes the least recently used files until the directory is at most `max_size` bytes.
+fn prune_cache(dir: &std::path::Path, max_size: u64) -> Result<()> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if metadata.is_file() {
+            files.push((metadata.modified()?, metadata.len(), entry.path()));
+        }
+    }
+
+    let mut total: u64 = files.iter().map(|(_, len, _)| len).sum();
+    files.sort();
+    for (_, len, path) in files {
+        if total <= max_size {
+            break;
+        }
+        std::fs::remove_file(path)?;
+        total -= len;
+    }
+    Ok(())
+}
+
 pub fn render_office(
     ctx: &KvContext,
     data: &[u8],
     extension: &str,
 ) -> Result<DynamicImage> {
+    check_office_data(data, extension)?;
+
     let hash = Sha256::digest(data);
     let hash_str = hex::encode(hash);
 
@@ -240,7 +907,7 @@ pub fn render_office(
 
     eprintln!("Converting office document to PDF...");
     // mute soffice output
-    Command::new("soffice")
+    let status = Command::new("soffice")
         .arg("--headless")
         .arg("--convert-to")
         .arg("pdf")
@@ -253,10 +920,49 @@ pub fn render_office(
         .context("Failed to convert office document to PDF")?;
 
     let pdf_path = target_dir.join(format!("{}.pdf", hash_str));
+    // soffice might exit successfully without writing a PDF for unreadable documents
+    if !status.success() || !pdf_path.exists() {
+        anyhow::bail!("LibreOffice could not convert the document (is it corrupt or mislabeled?)");
+    }
     let pdf_data = std::fs::read(&pdf_path)?;
     render_pdf(ctx, &pdf_data)
 }
 
+/// Extracts the frame at `time` seconds as PNG with ffmpeg, `None` if the video is shorter.
+#[cfg(feature = "video")]
+fn extract_video_frame(input: &std::path::Path, time: f32) -> Result<Option<Vec<u8>>> {
+    let output = Command::new("ffmpeg")
+        .args(["-v", "error", "-ss", &time.to_string(), "-i"])
+        .arg(input)
+        .args(["-frames:v", "1", "-f", "image2pipe", "-c:v", "png", "pipe:1"])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => anyhow::anyhow!("ffmpeg not found; install it to preview videos"),
+            _ => anyhow::Error::new(e).context("Failed to run ffmpeg"),
+        })?;
+    if !output.status.success() {
+        anyhow::bail!("ffmpeg could not decode the video (is it corrupt or mislabeled?)");
+    }
+    Ok((!output.stdout.is_empty()).then_some(output.stdout))
+}
+
+/// Renders a single frame of a video, at `ctx.video_time` or the first frame of shorter videos.
+#[cfg(feature = "video")]
+pub fn render_video(ctx: &KvContext, data: &[u8]) -> Result<DynamicImage> {
+    // containers like mp4 need seeking, so ffmpeg cannot read them from a pipe
+    let mut input = tempfile::NamedTempFile::new()?;
+    input.write_all(data)?;
+
+    let frame = match extract_video_frame(input.path(), ctx.video_time)? {
+        Some(frame) => frame,
+        None => extract_video_frame(input.path(), 0.0)?.context("Video has no frames")?,
+    };
+    let img = image::load_from_memory(&frame).context("Failed to decode video frame")?;
+    render_image(ctx, img)
+}
+
 pub fn render_plugin(ctx: &KvContext, data: &[u8], plugin: &Plugin) -> Result<DynamicImage> {
     let temp_dir_guard = tempfile::tempdir()?;
     let mut command_parts = shell_words::split(&plugin.path)
@@ -1,4 +1,6 @@
 use super::*;
+use crate::{PageRange, DEFAULT_MAX_PAGES};
+use image::imageops::FilterType;
 use image::{GenericImageView, Rgba};
 use rstest::rstest;
 
@@ -7,9 +9,72 @@ const PDF_DATA: &[u8] = include_bytes!("../../fixtures/test.pdf");
 const HTML_DATA: &[u8] = include_bytes!("../../fixtures/test.html");
 const RANDOM_DATA: &[u8] = include_bytes!("../../fixtures/test.random");
 
+fn default_ctx() -> KvContext {
+    KvContext {
+        input_type: InputType::Auto,
+        resize_mode: ResizeMode::ClipTerminal,
+        resize_filter: FilterType::Triangle,
+        max_width: None,
+        max_height: None,
+        crop: None,
+        rotate: 0,
+        flip_h: false,
+        flip_v: false,
+        brightness: 0,
+        contrast: 0.0,
+        gamma: 1.0,
+        grayscale: false,
+        sepia: false,
+        invert: false,
+        term_size: (100, 50),
+        pages: None,
+        keep_page_order: false,
+        max_pages: DEFAULT_MAX_PAGES,
+        pdf_dpi: None,
+        svg_scale: 1.0,
+        svg_color: None,
+        svg_stylesheet: None,
+        font_dirs: vec![],
+        font_files: vec![],
+        no_system_fonts: false,
+        page_layout: PageLayout::Vertical,
+        viewport: None,
+        html_wait: None,
+        html_delay: None,
+        html_capture_format: CaptureFormat::Png,
+        html_scale: 1.0,
+        chrome_path: None,
+        chrome_args: vec![],
+        no_sandbox: false,
+        page_gap: 0,
+        page_gap_color: Rgba([128, 128, 128, 255]),
+        separate_pages: false,
+        cache_mode: CacheMode::Disabled,
+        render_cache: false,
+        cache_size: 0,
+        video_time: 1.0,
+        progress: false,
+        background: None,
+    }
+}
+
+fn pdf_ctx(conf_w: Option<u32>, term_width: u32, page_indices: Option<Vec<u32>>) -> KvContext {
+    let mut ctx = default_ctx();
+    if conf_w.is_some() {
+        ctx.resize_mode = ResizeMode::Manual {
+            width: conf_w,
+            height: None,
+            fit: Fit::Stretch,
+        };
+    }
+    ctx.term_size = (term_width, 1000);
+    ctx.pages = page_indices.map(|pages| pages.into_iter().map(PageRange::from).collect());
+    ctx
+}
+
 #[test]
 fn test_render_svg() {
-    let result = render_svg(SVG_DATA);
+    let result = render_svg(&default_ctx(), SVG_DATA);
     assert!(result.is_ok(), "SVG generation failed");
 
     let img = result.unwrap();
@@ -20,14 +85,375 @@ fn test_render_svg() {
     assert_eq!(pixel, Rgba([102, 102, 102, 255]));
 }
 
+#[rstest]
+#[case(4.0, 4)]
+#[case(2.5, 3)]
+#[case(1000.0, 50)] // clipped to the terminal
+fn test_render_svg_scale(#[case] scale: f32, #[case] expected: u32) {
+    let mut ctx = default_ctx();
+    ctx.svg_scale = scale;
+    let img = render_svg(&ctx, SVG_DATA).unwrap();
+    assert_eq!(img.dimensions(), (expected, expected));
+    assert_eq!(img.get_pixel(expected / 2, expected / 2), Rgba([102, 102, 102, 255]));
+}
+
+#[rstest]
+#[case(Some(Rgba([255, 0, 0, 255])), None, Rgba([255, 0, 0, 255]))]
+#[case(None, Some("rect { fill: #00ff00; }"), Rgba([0, 255, 0, 255]))]
+#[case(Some(Rgba([255, 0, 0, 255])), Some("svg { color: blue; }"), Rgba([0, 0, 255, 255]))] // stylesheet wins
+#[case(None, None, Rgba([0, 0, 0, 255]))]
+fn test_render_svg_style(
+    #[case] svg_color: Option<Rgba<u8>>,
+    #[case] svg_stylesheet: Option<&str>,
+    #[case] expected: Rgba<u8>,
+) {
+    let svg_data = br#"<svg xmlns="http://www.w3.org/2000/svg" width="2" height="2"><rect width="2" height="2" fill="currentColor"/></svg>"#;
+    let mut ctx = default_ctx();
+    ctx.svg_color = svg_color;
+    ctx.svg_stylesheet = svg_stylesheet.map(str::to_string);
+    let img = render_svg(&ctx, svg_data).unwrap();
+    assert_eq!(img.get_pixel(1, 1), expected);
+}
+
+#[test]
+fn test_render_svg_missing_font_file() {
+    let mut ctx = default_ctx();
+    ctx.no_system_fonts = true;
+    ctx.font_files = vec![PathBuf::from("fixtures/missing.ttf")];
+    let result = render_svg(&ctx, SVG_DATA);
+    assert!(result.unwrap_err().to_string().contains("fixtures/missing.ttf"));
+}
+
+#[rstest]
+#[case(r#"width="0.5" height="0.5""#, 1, 1)]
+#[case(r#"width="1.4" height="2.6""#, 2, 3)]
+#[case(r#"width="0" height="0" viewBox="0 0 10 20""#, 10, 20)]
+#[case(r#"width="0" height="0""#, 50, 50)] // 512x512 default canvas, fit into the terminal
+fn test_render_svg_size(#[case] attrs: &str, #[case] width: u32, #[case] height: u32) {
+    let svg_data = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" {}><rect width="100%" height="100%"/></svg>"#,
+        attrs
+    );
+    let img = render_svg(&default_ctx(), svg_data.as_bytes()).unwrap();
+    assert_eq!(img.dimensions(), (width, height));
+}
+
+#[test]
+fn test_render_cached() {
+    let cache_dir = tempfile::tempdir().unwrap();
+    let mut ctx = default_ctx();
+    ctx.cache_mode = CacheMode::Custom(cache_dir.path().to_path_buf());
+    ctx.render_cache = true;
+    ctx.cache_size = u64::MAX;
+
+    let img = render_cached(&ctx, SVG_DATA, render_svg).unwrap();
+    let cached = render_cached(&ctx, SVG_DATA, |_, _| anyhow::bail!("not cached")).unwrap();
+    assert_eq!(img.to_rgba8(), cached.to_rgba8());
+
+    // options that do not change the image share the entry
+    ctx.progress = true;
+    ctx.cache_size = 1 << 40;
+    assert!(render_cached(&ctx, SVG_DATA, |_, _| anyhow::bail!("not cached")).is_ok());
+
+    // other options are rendered again
+    ctx.svg_scale = 2.0;
+    assert!(render_cached(&ctx, SVG_DATA, |_, _| anyhow::bail!("not cached")).is_err());
+}
+
+#[test]
+fn test_render_cached_font_change() {
+    let cache_dir = tempfile::tempdir().unwrap();
+    let font = cache_dir.path().join("font.ttf");
+    std::fs::write(&font, "first").unwrap();
+    let mut ctx = default_ctx();
+    ctx.cache_mode = CacheMode::Custom(cache_dir.path().to_path_buf());
+    ctx.render_cache = true;
+    ctx.cache_size = u64::MAX;
+    ctx.font_files = vec![font.clone()];
+
+    let img = DynamicImage::new_rgba8(1, 1);
+    render_cached(&ctx, SVG_DATA, |_, _| Ok(img.clone())).unwrap();
+    assert!(render_cached(&ctx, SVG_DATA, |_, _| anyhow::bail!("not cached")).is_ok());
+
+    // same path, edited font
+    std::fs::write(&font, "second").unwrap();
+    assert!(render_cached(&ctx, SVG_DATA, |_, _| anyhow::bail!("not cached")).is_err());
+}
+
+#[test]
+fn test_prune_cache() {
+    let cache_dir = tempfile::tempdir().unwrap();
+    for (name, age) in [("old", 20), ("mid", 10), ("new", 0)] {
+        let path = cache_dir.path().join(name);
+        std::fs::write(&path, [0u8; 10]).unwrap();
+        let modified = std::time::SystemTime::now() - std::time::Duration::from_secs(age);
+        std::fs::File::options().write(true).open(&path).unwrap().set_modified(modified).unwrap();
+    }
+    prune_cache(cache_dir.path(), 25).unwrap();
+    let mut names: Vec<_> = std::fs::read_dir(cache_dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    names.sort();
+    assert_eq!(names, ["mid", "new"]);
+}
+
+#[test]
+fn test_render_svg_crop() {
+    // left half red, right half blue
+    let svg_data = br#"<svg xmlns="http://www.w3.org/2000/svg" width="4" height="2"><rect width="2" height="2" fill="red"/><rect x="2" width="2" height="2" fill="blue"/></svg>"#;
+    let mut ctx = default_ctx();
+    ctx.crop = Some((2, 0, 2, 2));
+    ctx.rotate = 90;
+    let img = render_svg(&ctx, svg_data).unwrap();
+    assert_eq!(img.dimensions(), (2, 2));
+    assert_eq!(img.get_pixel(0, 0), Rgba([0, 0, 255, 255]));
+}
+
 #[test]
 fn test_render_svg_invalid() {
     let svg_data = br#"<svg>invalid"#;
 
-    let result = render_svg(svg_data);
+    let result = render_svg(&default_ctx(), svg_data);
     assert!(result.is_err(), "SVG generation failed");
 }
 
+const BLACK: Rgba<u8> = Rgba([0, 0, 0, 255]);
+const GRAY: Rgba<u8> = Rgba([128, 128, 128, 255]);
+const WHITE: Rgba<u8> = Rgba([255, 255, 255, 255]);
+const LIGHT: Rgba<u8> = Rgba([204, 204, 204, 255]);
+const DARK: Rgba<u8> = Rgba([153, 153, 153, 255]);
+
+#[rstest]
+#[case(WHITE, Rgba([255, 0, 0, 128]), Rgba([255, 127, 127, 255]))]
+#[case(Rgba([0, 0, 255, 128]), Rgba([255, 0, 0, 128]), Rgba([170, 0, 84, 192]))]
+#[case(Rgba([0, 0, 255, 128]), Rgba([0, 0, 0, 0]), Rgba([0, 0, 255, 128]))]
+#[case(Rgba([0, 0, 0, 0]), Rgba([255, 0, 0, 128]), Rgba([255, 0, 0, 128]))]
+#[case(Rgba([0, 0, 255, 128]), Rgba([255, 0, 0, 255]), Rgba([255, 0, 0, 255]))]
+fn test_add_background_alpha(
+    #[case] color: Rgba<u8>,
+    #[case] src_pixel: Rgba<u8>,
+    #[case] expected: Rgba<u8>,
+) {
+    let mut img = DynamicImage::new_rgba8(1, 1);
+    img.as_mut_rgba8().unwrap().put_pixel(0, 0, src_pixel);
+    let result = add_background(&img, &color);
+    assert_eq!(result.get_pixel(0, 0), expected);
+}
+
+#[rstest]
+#[case(0, 0, LIGHT)]
+#[case(1, 1, LIGHT)]
+#[case(2, 0, DARK)]
+#[case(0, 2, DARK)]
+#[case(2, 2, LIGHT)]
+fn test_add_checkerboard(#[case] x: u32, #[case] y: u32, #[case] expected: Rgba<u8>) {
+    let img = DynamicImage::new_rgba8(4, 4); // fully transparent
+    let result = add_checkerboard(&img, 2, &LIGHT, &DARK);
+    assert_eq!(result.get_pixel(x, y), expected);
+}
+
+#[rstest]
+#[case(Rgba([255, 0, 0, 255]), Rgba([255, 0, 0, 255]))]
+#[case(Rgba([255, 0, 0, 128]), Rgba([229, 101, 101, 255]))]
+fn test_add_checkerboard_blend(#[case] src_pixel: Rgba<u8>, #[case] expected: Rgba<u8>) {
+    let mut img = DynamicImage::new_rgba8(1, 1);
+    img.as_mut_rgba8().unwrap().put_pixel(0, 0, src_pixel);
+    let result = apply_background(&img, &Background::Checkerboard(8));
+    assert_eq!(result.get_pixel(0, 0), expected);
+}
+
+#[rstest]
+#[case(true, (0, 0), BLACK)]
+#[case(true, (2, 0), BLACK)]
+#[case(true, (0, 1), GRAY)]
+#[case(true, (0, 2), WHITE)]
+#[case(false, (0, 2), BLACK)]
+#[case(false, (1, 0), GRAY)]
+#[case(false, (2, 2), WHITE)]
+fn test_add_gradient_background(
+    #[case] vertical: bool,
+    #[case] (x, y): (u32, u32),
+    #[case] expected: Rgba<u8>,
+) {
+    let img = DynamicImage::new_rgba8(3, 3); // fully transparent
+    let result = add_gradient_background(&img, &BLACK, &WHITE, vertical);
+    assert_eq!(result.get_pixel(x, y), expected);
+}
+
+#[rstest]
+#[case(FilterType::Nearest, BLACK)]
+#[case(FilterType::Triangle, Rgba([64, 64, 64, 255]))]
+fn test_render_image_filter(#[case] filter: FilterType, #[case] expected: Rgba<u8>) {
+    let mut img = RgbaImage::from_pixel(2, 1, BLACK);
+    img.put_pixel(1, 0, WHITE);
+    let mut ctx = default_ctx();
+    ctx.resize_mode = ResizeMode::Manual { width: Some(4), height: None, fit: Fit::Stretch };
+    ctx.resize_filter = filter;
+    let result = render_image(&ctx, DynamicImage::ImageRgba8(img)).unwrap();
+    assert_eq!(result.get_pixel(1, 0), expected);
+}
+
+#[test]
+fn test_render_image_max_width() {
+    // applies even without resizing
+    let mut ctx = default_ctx();
+    ctx.resize_mode = ResizeMode::Original;
+    ctx.max_width = Some(1000);
+    let img = DynamicImage::new_rgba8(4000, 300);
+    let result = render_image(&ctx, img).unwrap();
+    assert_eq!(result.dimensions(), (1000, 75));
+}
+
+#[test]
+fn test_render_image_fit_letterbox() {
+    let img = RgbaImage::from_pixel(4, 2, WHITE);
+    let mut ctx = default_ctx();
+    ctx.resize_mode = ResizeMode::Manual { width: Some(2), height: Some(3), fit: Fit::Letterbox };
+    let result = render_image(&ctx, DynamicImage::ImageRgba8(img)).unwrap();
+    // 2x1 image centered in the 2x3 box
+    assert_eq!(result.dimensions(), (2, 3));
+    assert_eq!(result.get_pixel(0, 0), Rgba([0, 0, 0, 0]));
+    assert_eq!(result.get_pixel(0, 1), WHITE);
+    assert_eq!(result.get_pixel(1, 2), Rgba([0, 0, 0, 0]));
+}
+
+#[test]
+fn test_render_image_fit_cover() {
+    // black, white, black columns: cover keeps the white center
+    let img = RgbaImage::from_fn(3, 1, |x, _| if x == 1 { WHITE } else { BLACK });
+    let mut ctx = default_ctx();
+    ctx.resize_mode = ResizeMode::Manual { width: Some(2), height: Some(2), fit: Fit::Cover };
+    ctx.resize_filter = FilterType::Nearest;
+    let result = render_image(&ctx, DynamicImage::ImageRgba8(img)).unwrap();
+    assert_eq!(result.dimensions(), (2, 2));
+    assert!(result.to_rgba8().pixels().any(|&p| p == WHITE));
+}
+
+#[rstest]
+#[case(0, false, false, (3, 2), (0, 0))]
+#[case(90, false, false, (2, 3), (1, 0))]
+#[case(180, false, false, (3, 2), (2, 1))]
+#[case(270, false, false, (2, 3), (0, 2))]
+#[case(0, true, false, (3, 2), (2, 0))]
+#[case(0, false, true, (3, 2), (0, 1))]
+#[case(90, true, false, (2, 3), (0, 0))] // rotated, then flipped
+fn test_render_image_orientation(
+    #[case] rotate: u32,
+    #[case] flip_h: bool,
+    #[case] flip_v: bool,
+    #[case] dimensions: (u32, u32),
+    #[case] (x, y): (u32, u32),
+) {
+    // 3x2 black image with a white top left pixel
+    let mut img = RgbaImage::from_pixel(3, 2, BLACK);
+    img.put_pixel(0, 0, WHITE);
+    let mut ctx = default_ctx();
+    ctx.rotate = rotate;
+    ctx.flip_h = flip_h;
+    ctx.flip_v = flip_v;
+    let result = render_image(&ctx, DynamicImage::ImageRgba8(img)).unwrap();
+    assert_eq!(result.dimensions(), dimensions);
+    assert_eq!(result.get_pixel(x, y), WHITE);
+}
+
+#[rstest]
+#[case((1, 0, 2, 2), Some((2, 2)))]
+#[case((0, 0, 3, 2), Some((3, 2)))]
+#[case((2, 1, 2, 1), None)] // outside the 3x2 image
+fn test_render_image_crop(#[case] crop: (u32, u32, u32, u32), #[case] expected: Option<(u32, u32)>) {
+    let mut img = RgbaImage::from_pixel(3, 2, BLACK);
+    img.put_pixel(1, 0, WHITE);
+    let mut ctx = default_ctx();
+    ctx.crop = Some(crop);
+    let result = render_image(&ctx, DynamicImage::ImageRgba8(img));
+    match expected {
+        Some(dimensions) => {
+            let result = result.unwrap();
+            assert_eq!(result.dimensions(), dimensions);
+            assert_eq!(result.get_pixel(1 - crop.0, 0), WHITE);
+        }
+        None => assert_eq!(
+            result.unwrap_err().to_string(),
+            "Crop region 2,1,2,1 is outside the 3x2 image"
+        ),
+    }
+}
+
+#[rstest]
+#[case(false, false, false, Rgba([255, 0, 0, 128]))]
+#[case(true, false, false, Rgba([54, 54, 54, 128]))] // Rec. 709 luminance of red
+#[case(false, true, false, Rgba([100, 89, 69, 128]))]
+#[case(false, false, true, Rgba([0, 255, 255, 128]))]
+#[case(true, false, true, Rgba([201, 201, 201, 128]))]
+#[case(true, true, true, Rgba([182, 190, 204, 128]))] // grayscale, then sepia, then invert
+fn test_render_image_color_filters(
+    #[case] grayscale: bool,
+    #[case] sepia: bool,
+    #[case] invert: bool,
+    #[case] expected: Rgba<u8>,
+) {
+    let img = RgbaImage::from_pixel(1, 1, Rgba([255, 0, 0, 128]));
+    let mut ctx = default_ctx();
+    ctx.grayscale = grayscale;
+    ctx.sepia = sepia;
+    ctx.invert = invert;
+    let result = render_image(&ctx, DynamicImage::ImageRgba8(img)).unwrap();
+    assert_eq!(result.get_pixel(0, 0), expected);
+}
+
+#[rstest]
+#[case(0, 0.0, 1.0, Rgba([64, 128, 192, 100]))] // no-op
+#[case(100, 0.0, 1.0, Rgba([164, 228, 255, 100]))] // clamped
+#[case(-100, 0.0, 1.0, Rgba([0, 28, 92, 100]))]
+#[case(0, 100.0, 1.0, Rgba([0, 130, 255, 100]))]
+#[case(0, 0.0, 2.0, Rgba([128, 181, 221, 100]))]
+#[case(0, 0.0, 0.5, Rgba([16, 64, 145, 100]))]
+fn test_render_image_adjustments(
+    #[case] brightness: i32,
+    #[case] contrast: f32,
+    #[case] gamma: f32,
+    #[case] expected: Rgba<u8>,
+) {
+    let img = RgbaImage::from_pixel(1, 1, Rgba([64, 128, 192, 100]));
+    let mut ctx = default_ctx();
+    ctx.brightness = brightness;
+    ctx.contrast = contrast;
+    ctx.gamma = gamma;
+    let result = render_image(&ctx, DynamicImage::ImageRgba8(img)).unwrap();
+    assert_eq!(result.get_pixel(0, 0), expected);
+}
+
+#[test]
+fn test_render_image_color_filters_background() {
+    // the background is filtered too
+    let img = RgbaImage::from_pixel(1, 1, Rgba([0, 0, 0, 0]));
+    let mut ctx = default_ctx();
+    ctx.background = Some(Background::Color(WHITE));
+    ctx.invert = true;
+    let result = render_image(&ctx, DynamicImage::ImageRgba8(img)).unwrap();
+    assert_eq!(result.get_pixel(0, 0), BLACK);
+}
+
+#[cfg(feature = "parallel")]
+#[rstest]
+#[case(1, 1)]
+#[case(7, 3)]
+#[case(64, 33)]
+fn test_blend_onto_parallel(#[case] width: u32, #[case] height: u32) {
+    let src = RgbaImage::from_fn(width, height, |x, y| {
+        Rgba([(x * 7) as u8, (y * 13) as u8, (x + y) as u8, ((x * y) % 256) as u8])
+    });
+    let bg = RgbaImage::from_pixel(width, height, Rgba([10, 200, 30, 255]));
+
+    let mut serial = bg.clone();
+    blend_onto_serial(&mut serial, &src);
+    let mut parallel = bg;
+    blend_onto_parallel(&mut parallel, &src);
+
+    assert_eq!(serial, parallel);
+}
+
 #[rstest]
 #[case(None, 100, None, 100)]
 #[case(None, 100, Some(vec![0]), 100)]
@@ -35,10 +461,10 @@ fn test_render_svg_invalid() {
 fn test_render_pdf(
     #[case] conf_w: Option<u32>,
     #[case] term_width: u32,
-    #[case] page_indices: Option<Vec<u16>>,
+    #[case] page_indices: Option<Vec<u32>>,
     #[case] expected_width: u32,
 ) {
-    let result = render_pdf(PDF_DATA, conf_w, term_width, page_indices);
+    let result = render_pdf(&pdf_ctx(conf_w, term_width, page_indices), PDF_DATA);
     assert!(result.is_ok(), "PDF generation failed");
 
     let img = result.unwrap();
@@ -48,20 +474,72 @@ fn test_render_pdf(
     assert_eq!(pixel, Rgba([255, 255, 255, 255]));
 }
 
+#[rstest]
+#[case(72.0)]
+#[case(144.0)]
+fn test_render_pdf_dpi(#[case] dpi: f32) {
+    let mut ctx = pdf_ctx(None, 10000, None);
+    ctx.term_size = (10000, 10000);
+    ctx.pdf_dpi = Some(dpi);
+    let result = render_pdf(&ctx, PDF_DATA);
+    assert!(result.is_ok(), "PDF generation failed");
+
+    // the fixture page is 500 x 800 pt
+    let img = result.unwrap();
+    assert_eq!(img.width(), (500.0 * dpi / 72.0).round() as u32);
+}
+
+#[rstest]
+#[case(PageLayout::Vertical, 0, 4, 7)]
+#[case(PageLayout::Vertical, 2, 4, 11)]
+#[case(PageLayout::Horizontal, 0, 12, 4)]
+#[case(PageLayout::Horizontal, 2, 16, 4)]
+#[case(PageLayout::Grid(2), 0, 8, 8)]
+#[case(PageLayout::Grid(2), 2, 10, 10)]
+#[case(PageLayout::Grid(5), 0, 12, 4)]
+fn test_combine_pages(
+    #[case] layout: PageLayout,
+    #[case] gap: u32,
+    #[case] width: u32,
+    #[case] height: u32,
+) {
+    let red = Rgba([255, 0, 0, 255]);
+    let gray = Rgba([128, 128, 128, 255]);
+    let pages = vec![
+        RgbaImage::from_pixel(4, 4, red),
+        RgbaImage::from_pixel(2, 1, red),
+        RgbaImage::from_pixel(4, 2, red),
+    ];
+    let combined = combine_pages(pages, layout, gap, gray).unwrap();
+    assert_eq!(combined.dimensions(), (width, height));
+    if layout == PageLayout::Vertical {
+        if gap > 0 {
+            assert_eq!(combined.get_pixel(3, 4), &gray);
+        }
+    } else {
+        // the small second page is centered in its cell and padded with white
+        assert_eq!(combined.get_pixel(4 + gap, 0), &Rgba([255, 255, 255, 255]));
+        assert_eq!(combined.get_pixel(5 + gap, 1), &red);
+        if gap > 0 {
+            assert_eq!(combined.get_pixel(4, 0), &gray);
+        }
+    }
+}
+
 #[test]
 fn test_render_pdf_invalid() {
     let pdf_data = br#"%PDF-1.4
 invalid"#;
 
-    let result = render_pdf(pdf_data, None, 100, None);
+    let result = render_pdf(&pdf_ctx(None, 100, None), pdf_data);
     assert!(result.is_err(), "PDF generation failed");
 }
 
 #[rstest]
 #[case(vec![])]
 #[case(vec![2])]
-fn test_render_pdf_out_of_range(#[case] page_indices: Vec<u16>) {
-    let result = render_pdf(PDF_DATA, None, 100, Some(page_indices));
+fn test_render_pdf_out_of_range(#[case] page_indices: Vec<u32>) {
+    let result = render_pdf(&pdf_ctx(None, 100, Some(page_indices)), PDF_DATA);
     assert!(result.is_err(), "PDF generation failed");
 }
 
@@ -70,7 +548,7 @@ fn test_render_pdf_out_of_range(#[case] page_indices: Vec<u16>) {
 #[case(b"fixtures/test.html")]
 #[case(b"https://commons.wikimedia.org/wiki/File:Solid_red.png")]
 fn test_render_html_chrome(#[case] html_data: &[u8]) {
-    let result = render_html_chrome(html_data);
+    let result = render_html_chrome(&default_ctx(), html_data);
     assert!(result.is_ok(), "HTML generation failed");
 
     let img = result.unwrap();
@@ -92,6 +570,21 @@ fn test_render_html_chrome(#[case] html_data: &[u8]) {
 #[rstest]
 #[case(RANDOM_DATA)] // non-utf-8
 fn test_render_html_chrome_invalid(#[case] html_data: &[u8]) {
-    let result = render_html_chrome(html_data);
+    let result = render_html_chrome(&default_ctx(), html_data);
     assert!(result.is_err(), "HTML generation should fail");
 }
+
+#[rstest]
+#[case(b"PK\x03\x04....word/document.xml....", "docx", None)]
+#[case(b"PK\x03\x04....content.xml....", "odt", None)]
+#[case(b"\xD0\xCF\x11\xE0\xA1\xB1\x1A\xE1....", "doc", None)] // binary formats are not checked
+#[case(b"PK\x03\x04....xl/workbook.xml....", "docx", Some("Not a valid DOCX: missing word/document.xml"))]
+#[case(b"\xD0\xCF\x11\xE0\xA1\xB1\x1A\xE1....", "pptx", Some("Not a valid PPTX: this is a legacy binary .ppt file, rename or convert it"))]
+#[case(b"plain text", "ods", Some("Not a valid ODS: not a zip archive"))]
+fn test_check_office_data(#[case] data: &[u8], #[case] extension: &str, #[case] expected_error: Option<&str>) {
+    let result = check_office_data(data, extension);
+    match expected_error {
+        Some(error) => assert_eq!(result.unwrap_err().to_string(), error),
+        None => assert!(result.is_ok()),
+    }
+}
@@ -1,36 +1,130 @@
 use anyhow::{Context, Result};
 use base64::{engine::general_purpose, Engine as _};
 use bat::{Input, PrettyPrinter};
+use color_quant::NeuQuant;
 use flate2::write::ZlibEncoder;
 use flate2::Compression;
+use image::codecs::bmp::BmpEncoder;
+use image::codecs::jpeg::JpegEncoder;
 use image::codecs::png::PngEncoder;
-use image::{DynamicImage, GenericImageView, ImageEncoder};
-use std::io::{Cursor, Write};
+use image::codecs::webp::WebPEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ExtendedColorType, GenericImageView, ImageEncoder, RgbaImage};
+use std::borrow::Cow;
+use std::io::{Cursor, Read, Write};
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, Ordering};
 
-const KITTY_CHUNK_SIZE: usize = 4096;
-const INPUT_CHUNK_SIZE: usize = (KITTY_CHUNK_SIZE * 3) / 4;
+use crate::diacritics::ROWCOLUMN_DIACRITICS;
+use crate::AnimationFrame;
+
+/// Default size of the base64 data in each kitty chunk.
+pub const KITTY_CHUNK_SIZE: usize = 4096;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Mode {
     Png,
     Zlib,
     Raw,
+    /// DEC Sixel graphics instead of the kitty protocol.
+    Sixel,
+    /// iTerm2 inline image protocol (also supported by WezTerm), PNG payload.
+    Iterm2,
+    /// Unicode half blocks with 24-bit ANSI colors, for terminals without graphics support.
+    Blocks,
+}
+
+impl Mode {
+    /// Whether the mode uses the kitty graphics protocol.
+    pub fn is_kitty(self) -> bool {
+        matches!(self, Mode::Png | Mode::Zlib | Mode::Raw)
+    }
+}
+
+/// Transmission medium of kitty graphics payloads.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Transfer {
+    /// Inline base64 data in chunked escape sequences (`t=d`).
+    Direct,
+    /// Write the payload to a temporary file the terminal reads and deletes (`t=t`).
+    File,
+}
+
+/// Image format written to output files, chosen by the file extension.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    Png,
+    /// Lossy, encoded with `SendOptions::quality`; transparency is dropped.
+    Jpeg,
+    /// Lossless, the only WebP encoding `image` supports.
+    Webp,
+    Bmp,
+}
+
+impl OutputFormat {
+    /// Returns the format for the extension of the output path, PNG if it is unknown.
+    pub fn from_path(path: &str) -> Self {
+        let extension = std::path::Path::new(path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("")
+            .to_lowercase();
+        match extension.as_str() {
+            "jpg" | "jpeg" => OutputFormat::Jpeg,
+            "webp" => OutputFormat::Webp,
+            "bmp" => OutputFormat::Bmp,
+            _ => OutputFormat::Png,
+        }
+    }
+}
+
+/// Encodes the image for an output file in a format other than PNG.
+fn encode_output(img: &DynamicImage, format: OutputFormat, quality: u8) -> Result<Vec<u8>> {
+    let mut buffer = Vec::new();
+    match format {
+        OutputFormat::Png => return encode_payload(img, Mode::Png, &SendOptions::default()),
+        OutputFormat::Jpeg => {
+            let rgb = img.to_rgb8();
+            JpegEncoder::new_with_quality(&mut buffer, quality)
+                .encode_image(&rgb)
+                .context("Failed to encode image to JPEG")?;
+        }
+        OutputFormat::Webp => {
+            let rgba = img.to_rgba8();
+            WebPEncoder::new_lossless(&mut buffer)
+                .encode(rgba.as_raw(), rgba.width(), rgba.height(), ExtendedColorType::Rgba8)
+                .context("Failed to encode image to WebP")?;
+        }
+        OutputFormat::Bmp => {
+            let rgba = img.to_rgba8();
+            BmpEncoder::new(&mut buffer)
+                .encode(rgba.as_raw(), rgba.width(), rgba.height(), ExtendedColorType::Rgba8)
+                .context("Failed to encode image to BMP")?;
+        }
+    }
+    Ok(buffer)
 }
 
-pub enum PrinterInput {
+/// Kitty only deletes temporary files whose name contains this string.
+const TEMP_FILE_MARKER: &str = "tty-graphics-protocol";
+
+/// Character of kitty Unicode placeholder cells.
+const PLACEHOLDER_CHAR: char = '\u{10EEEE}';
+
+/// Number of palette entries used for Sixel output.
+const SIXEL_COLORS: usize = 256;
+
+pub enum PrinterInput<'a> {
     File(PathBuf),
     Data(Vec<u8>),
+    /// Streamed without buffering, e.g. stdin.
+    Reader(Box<dyn Read + 'a>),
 }
 
-pub fn send_image(
-    writer: &mut dyn Write,
-    img: DynamicImage,
-    output: Option<String>,
-    mode: Mode,
-) -> Result<()> {
+/// Encodes the image into the payload for the given transmission mode.
+fn encode_payload(img: &DynamicImage, mode: Mode, opts: &SendOptions) -> Result<Vec<u8>> {
     let payload = match mode {
-        Mode::Png => {
+        Mode::Png | Mode::Iterm2 => {
             let mut buffer = Vec::new();
             let (width, height) = img.dimensions();
             let color_type = img.color();
@@ -45,6 +139,8 @@ pub fn send_image(
             buffer
         }
         Mode::Raw => img.to_rgba8().into_raw(),
+        Mode::Sixel => encode_sixel(img, opts.dither),
+        Mode::Blocks => encode_blocks(img, opts),
         Mode::Zlib => {
             let raw_bytes = img.to_rgba8().into_raw();
             let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
@@ -52,57 +148,632 @@ pub fn send_image(
             encoder.finish().context("Failed to compress image data")?
         }
     };
+    Ok(payload)
+}
 
-    if output.is_some() {
-        writer.write_all(&payload)?;
-        return Ok(());
+/// Returns the format keys of the control header for the given mode.
+// Png: f=100
+// Raw: f=32,s={w},v={h} (+ o=z if zlib)
+fn format_keys(img: &DynamicImage, mode: Mode) -> String {
+    let (width, height) = img.dimensions();
+    match mode {
+        Mode::Png | Mode::Sixel | Mode::Iterm2 | Mode::Blocks => "f=100".to_string(),
+        Mode::Zlib => format!("f=32,s={},v={},o=z", width, height),
+        Mode::Raw => format!("f=32,s={},v={}", width, height),
+    }
+}
+
+/// Maps each pixel to its palette index, `None` for pixels with alpha < 128.
+/// With `dither`, the quantization error is diffused to the neighbouring pixels (Floyd–Steinberg).
+pub(crate) fn quantize(rgba: &RgbaImage, quant: &NeuQuant, dither: bool) -> Vec<Option<usize>> {
+    if !dither {
+        return rgba.pixels().map(|p| (p[3] >= 128).then(|| quant.index_of(&p.0))).collect();
     }
-    // Png: a=T,f=100
-    // Raw: a=T,f=32,s={w},v={h} (+ o=z if zlib)
+
+    let (width, height) = (rgba.width() as usize, rgba.height() as usize);
+    let palette = quant.color_map_rgb();
+    let mut colors: Vec<[f32; 3]> = rgba.pixels().map(|p| [p[0] as f32, p[1] as f32, p[2] as f32]).collect();
+    let mut indices = Vec::with_capacity(width * height);
+
+    for y in 0..height {
+        for x in 0..width {
+            let i = y * width + x;
+            let alpha = rgba.as_raw()[i * 4 + 3];
+            if alpha < 128 {
+                // transparent pixels neither take nor pass on error
+                indices.push(None);
+                continue;
+            }
+            let color = colors[i].map(|c| c.round().clamp(0.0, 255.0) as u8);
+            let index = quant.index_of(&[color[0], color[1], color[2], alpha]);
+            indices.push(Some(index));
+
+            let error: [f32; 3] = std::array::from_fn(|c| colors[i][c] - palette[index * 3 + c] as f32);
+            let mut spread = |dx: isize, dy: usize, weight: f32| {
+                let nx = x as isize + dx;
+                if nx < 0 || nx as usize >= width || y + dy >= height {
+                    return;
+                }
+                let n = (y + dy) * width + nx as usize;
+                for c in 0..3 {
+                    colors[n][c] += error[c] * weight;
+                }
+            };
+            spread(1, 0, 7.0 / 16.0);
+            spread(-1, 1, 3.0 / 16.0);
+            spread(0, 1, 5.0 / 16.0);
+            spread(1, 1, 1.0 / 16.0);
+        }
+    }
+    indices
+}
+
+/// Encodes the image as a Sixel escape sequence, quantized to a 256-color palette.
+/// Pixels with alpha < 128 are left transparent.
+fn encode_sixel(img: &DynamicImage, dither: bool) -> Vec<u8> {
+    let rgba = img.to_rgba8();
+    let (width, height) = rgba.dimensions();
+    let quant = NeuQuant::new(10, SIXEL_COLORS, rgba.as_raw());
+    let indices = quantize(&rgba, &quant, dither);
+
+    // P2=1: transparent pixels keep the terminal background
+    let mut out = Vec::new();
+    let _ = write!(out, "\x1bP0;1;q\"1;1;{};{}", width, height);
+
+    // palette, channels in percent
+    for (i, color) in quant.color_map_rgb().chunks_exact(3).enumerate() {
+        let pct = |c: u8| (c as u32 * 100 + 127) / 255;
+        let _ = write!(out, "#{};2;{};{};{}", i, pct(color[0]), pct(color[1]), pct(color[2]));
+    }
+
+    // each band encodes 6 rows, one pass per color present in the band
+    for band_y in (0..height).step_by(6) {
+        let band_rows = (height - band_y).min(6);
+        let mut colors: Vec<usize> = (band_y..band_y + band_rows)
+            .flat_map(|y| {
+                let row = (y * width) as usize;
+                indices[row..row + width as usize].iter().flatten().copied()
+            })
+            .collect();
+        colors.sort_unstable();
+        colors.dedup();
+
+        for (n, &color) in colors.iter().enumerate() {
+            if n > 0 {
+                // carriage return: overlay the next color on the same band
+                out.push(b'$');
+            }
+            let _ = write!(out, "#{}", color);
+
+            let sixels = (0..width).map(|x| {
+                let bits = (0..band_rows).fold(0u8, |bits, dy| {
+                    let idx = ((band_y + dy) * width + x) as usize;
+                    if indices[idx] == Some(color) { bits | (1 << dy) } else { bits }
+                });
+                b'?' + bits
+            });
+            write_sixel_runs(&mut out, sixels);
+        }
+        // next band
+        out.push(b'-');
+    }
+
+    out.extend_from_slice(b"\x1b\\");
+    out
+}
+
+/// Encodes the image as rows of upper half blocks (`▀`), each cell showing two vertical pixels
+/// in its foreground and background color. The image is scaled down to the terminal cell grid.
+/// Pixels with alpha < 128 keep the terminal colors.
+fn encode_blocks(img: &DynamicImage, opts: &SendOptions) -> Vec<u8> {
     let (width, height) = img.dimensions();
-    let header = match mode {
-        Mode::Png => "a=T,f=100".to_string(),
-        Mode::Zlib => format!("a=T,f=32,s={},v={},o=z", width, height),
-        Mode::Raw => format!("a=T,f=32,s={},v={}", width, height),
+    let (cell_width, cell_height) = (opts.cell_size.0.max(1), opts.cell_size.1.max(1));
+    let cols = width.div_ceil(cell_width).max(1);
+    let rows = height.div_ceil(cell_height).max(1);
+    let rgba = img.resize_exact(cols, rows * 2, FilterType::Triangle).to_rgba8();
+    let color = |x: u32, y: u32| {
+        let p = rgba.get_pixel(x, y);
+        (p[3] >= 128).then_some((p[0], p[1], p[2]))
     };
 
+    let mut out = Vec::new();
+    for row in 0..rows {
+        if row > 0 {
+            if opts.is_positioned() {
+                // stay in the placement column
+                let _ = write!(out, "\x1b[1B\x1b[{}D", cols);
+            } else {
+                out.push(b'\n');
+            }
+        }
+        for x in 0..cols {
+            let _ = match (color(x, row * 2), color(x, row * 2 + 1)) {
+                (Some((r, g, b)), Some((br, bg, bb))) => {
+                    write!(out, "\x1b[38;2;{};{};{};48;2;{};{};{}m▀", r, g, b, br, bg, bb)
+                }
+                (Some((r, g, b)), None) => write!(out, "\x1b[38;2;{};{};{};49m▀", r, g, b),
+                (None, Some((r, g, b))) => write!(out, "\x1b[38;2;{};{};{};49m▄", r, g, b),
+                (None, None) => write!(out, "\x1b[0m "),
+            };
+        }
+        out.extend_from_slice(b"\x1b[0m");
+    }
+    out
+}
+
+/// Writes sixel characters with run-length encoding (`!<count><char>`).
+fn write_sixel_runs(out: &mut Vec<u8>, sixels: impl Iterator<Item = u8>) {
+    let flush = |out: &mut Vec<u8>, sixel: u8, count: usize| {
+        if count > 3 {
+            let _ = write!(out, "!{}", count);
+            out.push(sixel);
+        } else {
+            out.extend(std::iter::repeat_n(sixel, count));
+        }
+    };
+
+    let mut current: Option<(u8, usize)> = None;
+    for sixel in sixels {
+        current = match current {
+            Some((prev, count)) if prev == sixel => Some((prev, count + 1)),
+            Some((prev, count)) => {
+                flush(out, prev, count);
+                Some((sixel, 1))
+            }
+            None => Some((sixel, 1)),
+        };
+    }
+    if let Some((prev, count)) = current {
+        flush(out, prev, count);
+    }
+}
+
+/// Which kitty images a delete command (`a=d`) removes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RemoveMode {
+    /// All images.
+    All,
+    /// All placements, keeping the image data for later display by id.
+    Placements,
+    /// Placements intersecting the cursor cell.
+    Cursor,
+    /// Placements of the image with the given id.
+    Id(u32),
+}
+
+impl RemoveMode {
+    /// Returns the body of the kitty delete command.
+    pub fn command(&self) -> String {
+        match self {
+            RemoveMode::All => "a=d".to_string(),
+            RemoveMode::Placements => "a=d,d=a".to_string(),
+            RemoveMode::Cursor => "a=d,d=c".to_string(),
+            RemoveMode::Id(id) => format!("a=d,d=i,i={}", id),
+        }
+    }
+}
+
+/// Writes a single kitty graphics command (`\x1b_G<body>\x1b\\`).
+/// With `tmux`, the command is wrapped in a tmux passthrough sequence with its escapes doubled.
+pub fn write_graphics_command(writer: &mut dyn Write, body: &str, tmux: bool) -> Result<()> {
+    if tmux {
+        write!(writer, "\x1bPtmux;\x1b\x1b_G{}\x1b\x1b\\\x1b\\", body.replace('\x1b', "\x1b\x1b"))?;
+    } else {
+        write!(writer, "\x1b_G{}\x1b\\", body)?;
+    }
+    Ok(())
+}
+
+/// Writes the payload as chunked base64 graphics commands, with the control header on the first chunk.
+fn write_chunked(writer: &mut dyn Write, header: &str, payload: &[u8], opts: &SendOptions) -> Result<()> {
     let total_len = payload.len();
+    // chunk_size is a multiple of 4, so every chunk but the last is unpadded
+    let input_chunk_size = (opts.chunk_size / 4).max(1) * 3;
     let mut offset = 0;
 
     // reusable buffer
-    let mut b64_buffer = String::with_capacity(KITTY_CHUNK_SIZE + 4);
+    let mut body = String::with_capacity(header.len() + opts.chunk_size + 8);
 
     while offset < total_len {
-        let end = (offset + INPUT_CHUNK_SIZE).min(total_len);
+        let end = (offset + input_chunk_size).min(total_len);
         let chunk_data = &payload[offset..end];
+        let more = if end < total_len { 1 } else { 0 };
 
+        body.clear();
+        // send control header only on the first chunk
+        if offset == 0 {
+            body.push_str(header);
+            body.push(',');
+        }
+        body.push_str(if more == 1 { "m=1;" } else { "m=0;" });
         // encode chunk to base64
-        b64_buffer.clear();
-        general_purpose::STANDARD.encode_string(chunk_data, &mut b64_buffer);
+        general_purpose::STANDARD.encode_string(chunk_data, &mut body);
 
-        let more = if end < total_len { 1 } else { 0 };
+        write_graphics_command(writer, &body, opts.tmux)?;
 
-        write!(writer, "\x1b_G")?;
+        offset = end;
+    }
 
-        // send control header only on the first chunk
-        if offset == 0 {
-            write!(writer, "{},", header)?;
+    Ok(())
+}
+
+/// Writes the payload to a temporary file and sends its path (`t=t`).
+/// Only works if the terminal runs on the same host and can read the file.
+fn write_temp_file(writer: &mut dyn Write, header: &str, payload: &[u8], opts: &SendOptions) -> Result<()> {
+    let mut file = tempfile::Builder::new()
+        .prefix(&format!("{}-", TEMP_FILE_MARKER))
+        .tempfile()
+        .context("Failed to create temporary file")?;
+    file.write_all(payload)?;
+    // the terminal deletes the file after reading it
+    let (_, path) = file.keep().context("Failed to keep temporary file")?;
+
+    let body = format!(
+        "{},t=t;{}",
+        header,
+        general_purpose::STANDARD.encode(path.as_os_str().as_encoded_bytes())
+    );
+    write_graphics_command(writer, &body, opts.tmux)
+}
+
+/// Transmits the payload with the configured medium, falling back to inline data.
+fn write_payload(writer: &mut dyn Write, header: &str, payload: &[u8], opts: &SendOptions) -> Result<()> {
+    if opts.transfer == Transfer::File && write_temp_file(writer, header, payload, opts).is_ok() {
+        return Ok(());
+    }
+    write_chunked(writer, header, payload, opts)
+}
+
+/// Options controlling how images are sent to the terminal (or file).
+#[derive(Debug, Clone)]
+pub struct SendOptions {
+    /// Output path; if set, the encoded image is written instead of escape sequences.
+    pub output: Option<String>,
+    pub mode: Mode,
+    /// Loop animations forever instead of playing them once.
+    pub looping: bool,
+    /// Kitty image id (`i=`), assigned automatically where one is required.
+    pub image_id: Option<u32>,
+    /// Place the image with kitty Unicode placeholders instead of a direct placement.
+    pub placeholder: bool,
+    /// Terminal cell size in pixels, used to compute the placeholder rectangle.
+    pub cell_size: (u32, u32),
+    /// Column offset (0-based, in cells) to place the image at, restoring the cursor afterwards.
+    pub col: Option<u32>,
+    /// Row offset (0-based, in cells) to place the image at, restoring the cursor afterwards.
+    pub row: Option<u32>,
+    /// Place the image at the cursor without moving it or scrolling, like `col`/`row` do.
+    pub in_place: bool,
+    /// Kitty z-index (`z=`) of the placement; negative values draw below text.
+    pub z_index: Option<i32>,
+    /// Size of the base64 data in each kitty chunk (a multiple of 4).
+    pub chunk_size: usize,
+    /// Wrap kitty graphics commands in tmux passthrough sequences.
+    pub tmux: bool,
+    /// Kitty response suppression (`q=`): 1 suppresses OK responses, 2 also errors.
+    pub quiet: Option<u8>,
+    /// Transmission medium of kitty payloads.
+    pub transfer: Transfer,
+    /// Apply Floyd–Steinberg dithering before palette quantization (Sixel).
+    pub dither: bool,
+    /// Quality (1-100) of lossy output files.
+    pub quality: u8,
+}
+
+impl Default for SendOptions {
+    fn default() -> Self {
+        SendOptions {
+            output: None,
+            mode: Mode::Png,
+            looping: true,
+            image_id: None,
+            placeholder: false,
+            cell_size: (10, 20),
+            col: None,
+            row: None,
+            in_place: false,
+            z_index: None,
+            chunk_size: KITTY_CHUNK_SIZE,
+            tmux: false,
+            quiet: None,
+            transfer: Transfer::Direct,
+            dither: false,
+            quality: 90,
         }
+    }
+}
 
-        // send payload
-        write!(writer, "m={};", more)?;
-        writer.write_all(b64_buffer.as_bytes())?;
+impl SendOptions {
+    /// Whether the cursor is saved before and restored after the image.
+    fn is_positioned(&self) -> bool {
+        self.col.is_some() || self.row.is_some() || self.in_place
+    }
+
+    /// Saves the cursor and moves it to the requested cell, if any.
+    fn write_position(&self, writer: &mut dyn Write) -> Result<()> {
+        if !self.is_positioned() {
+            return Ok(());
+        }
+        write!(writer, "\x1b7")?;
+        match (self.row, self.col) {
+            (Some(row), Some(col)) => write!(writer, "\x1b[{};{}H", row + 1, col + 1)?,
+            (Some(row), None) => write!(writer, "\x1b[{}d", row + 1)?,
+            (None, Some(col)) => write!(writer, "\x1b[{}G", col + 1)?,
+            (None, None) => {}
+        }
+        Ok(())
+    }
 
-        // end escape sequence
-        write!(writer, "\x1b\\")?;
+    /// Finishes the output: restores the cursor if positioned, otherwise adds a newline.
+    fn write_end(&self, writer: &mut dyn Write) -> Result<()> {
+        if self.is_positioned() {
+            write!(writer, "\x1b8")?;
+        } else {
+            // ensure terminal is clean
+            writeln!(writer)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
 
-        offset = end;
+    /// Number of terminal cells (columns, rows) covered by the image, as used for placeholders.
+    fn cell_rect(&self, img: &DynamicImage) -> (u32, u32) {
+        let (width, height) = img.dimensions();
+        let max = ROWCOLUMN_DIACRITICS.len() as u32;
+        let cols = width.div_ceil(self.cell_size.0.max(1)).clamp(1, max);
+        let rows = height.div_ceil(self.cell_size.1.max(1)).clamp(1, max);
+        (cols, rows)
     }
 
-    // ensure terminal is clean
-    writeln!(writer)?;
-    writer.flush()?;
+    /// Level of suppressed terminal responses (`q=`).
+    /// Responses are only sent for commands with an id, so these are quiet unless configured otherwise.
+    fn quiet_level(&self) -> u8 {
+        self.quiet.unwrap_or(2)
+    }
 
+    /// Returns the image id and placement keys for the first control header.
+    fn placement_keys(&self, img: &DynamicImage, needs_id: bool) -> (Option<u32>, String) {
+        let id = if needs_id || self.placeholder {
+            Some(self.image_id.unwrap_or_else(next_image_id))
+        } else {
+            self.image_id
+        };
+        let mut keys = String::new();
+        if let Some(id) = id {
+            keys.push_str(&format!(",i={},q={}", id, self.quiet_level()));
+        } else if let Some(quiet) = self.quiet {
+            keys.push_str(&format!(",q={}", quiet));
+        }
+        if self.placeholder {
+            let (cols, rows) = self.cell_rect(img);
+            keys.push_str(&format!(",U=1,c={},r={}", cols, rows));
+        }
+        if let Some(z) = self.z_index {
+            keys.push_str(&format!(",z={}", z));
+        }
+        if self.in_place {
+            // do not move the cursor, which might scroll the terminal
+            keys.push_str(",C=1");
+        }
+        (id, keys)
+    }
+}
+
+/// Writes the Unicode placeholder cells for a virtual placement of the image `id`.
+/// The id is encoded in the 24-bit foreground color, its most significant byte in a third diacritic.
+fn write_placeholders(writer: &mut dyn Write, id: u32, (cols, rows): (u32, u32)) -> Result<()> {
+    let id_msb = (id >> 24) as usize;
+    for row_mark in &ROWCOLUMN_DIACRITICS[..rows as usize] {
+        write!(
+            writer,
+            "\x1b[38;2;{};{};{}m",
+            (id >> 16) & 0xFF,
+            (id >> 8) & 0xFF,
+            id & 0xFF
+        )?;
+        for col_mark in &ROWCOLUMN_DIACRITICS[..cols as usize] {
+            write!(writer, "{}{}{}", PLACEHOLDER_CHAR, row_mark, col_mark)?;
+            if id_msb > 0 {
+                write!(writer, "{}", ROWCOLUMN_DIACRITICS[id_msb])?;
+            }
+        }
+        writeln!(writer, "\x1b[39m")?;
+    }
+    Ok(())
+}
+
+pub fn send_image(writer: &mut dyn Write, img: DynamicImage, opts: &SendOptions) -> Result<()> {
+    send_image_with_png(writer, img, None, opts)
+}
+
+/// Sends an unmodified PNG image, transmitting its original bytes where the mode uses PNG.
+pub fn send_png(writer: &mut dyn Write, img: DynamicImage, png: &[u8], opts: &SendOptions) -> Result<()> {
+    send_image_with_png(writer, img, Some(png), opts)
+}
+
+/// Returns the escape sequences (or file data) `send_image` would write, for library use.
+pub fn encode_image(img: &DynamicImage, opts: &SendOptions) -> Result<Vec<u8>> {
+    let mut buffer = Vec::new();
+    send_image(&mut buffer, img.clone(), opts)?;
+    Ok(buffer)
+}
+
+fn send_image_with_png(
+    writer: &mut dyn Write,
+    img: DynamicImage,
+    png: Option<&[u8]>,
+    opts: &SendOptions,
+) -> Result<()> {
+    // files are written as PNG, unless their extension asks for another format
+    let format = opts.output.as_deref().map_or(OutputFormat::Png, OutputFormat::from_path);
+    if format != OutputFormat::Png {
+        writer.write_all(&encode_output(&img, format, opts.quality)?)?;
+        return Ok(());
+    }
+    let mode = if opts.output.is_some() && !opts.mode.is_kitty() { Mode::Png } else { opts.mode };
+    let payload = match png {
+        // skip the decode/encode round trip, which also keeps the PNG metadata
+        Some(png) if matches!(mode, Mode::Png | Mode::Iterm2) => Cow::Borrowed(png),
+        _ => Cow::Owned(encode_payload(&img, mode, opts)?),
+    };
+
+    if opts.output.is_some() {
+        writer.write_all(&payload)?;
+        return Ok(());
+    }
+
+    opts.write_position(writer)?;
+
+    if matches!(mode, Mode::Sixel | Mode::Blocks) {
+        writer.write_all(&payload)?;
+    } else if mode == Mode::Iterm2 {
+        // size in pixels, so the terminal does not rescale the already resized image
+        let (width, height) = img.dimensions();
+        write!(
+            writer,
+            "\x1b]1337;File=inline=1;size={};width={}px;height={}px;preserveAspectRatio=1:",
+            payload.len(),
+            width,
+            height
+        )?;
+        writer.write_all(general_purpose::STANDARD.encode(&payload).as_bytes())?;
+        write!(writer, "\x07")?;
+    } else {
+        let (id, keys) = opts.placement_keys(&img, false);
+        let header = format!("a=T{},{}", keys, format_keys(&img, mode));
+        write_payload(writer, &header, &payload, opts)?;
+
+        if let (true, Some(id)) = (opts.placeholder, id) {
+            write_placeholders(writer, id, opts.cell_rect(&img))?;
+            if opts.is_positioned() {
+                write!(writer, "\x1b8")?;
+            }
+            writer.flush()?;
+            return Ok(());
+        }
+    }
+
+    opts.write_end(writer)
+}
+
+/// Returns a new image id for commands that need to reference an image later.
+fn next_image_id() -> u32 {
+    static COUNTER: AtomicU32 = AtomicU32::new(0);
+    // mix in the process id to avoid clashes with other kv invocations
+    let id = (std::process::id() << 8) | (COUNTER.fetch_add(1, Ordering::Relaxed) & 0xFF);
+    id.max(1)
+}
+
+/// Transmits each page as its own image, one after another.
+pub fn send_pages(writer: &mut dyn Write, pages: Vec<DynamicImage>, opts: &SendOptions) -> Result<()> {
+    for page in pages {
+        send_image(writer, page, opts)?;
+    }
+    Ok(())
+}
+
+/// Transmits the frames as a kitty animation, looping forever unless disabled.
+/// When writing to a file, only the first frame is written.
+pub fn send_animation(
+    writer: &mut dyn Write,
+    frames: Vec<AnimationFrame>,
+    opts: &SendOptions,
+) -> Result<()> {
+    let mut frames = frames.into_iter();
+    let first = frames.next().context("Animation has no frames")?;
+    let mode = opts.mode;
+
+    // files and other protocols only show the first frame
+    if opts.output.is_some() || !mode.is_kitty() {
+        return send_image(writer, first.image, opts);
+    }
+
+    let (id, keys) = opts.placement_keys(&first.image, true);
+    let id = id.context("Animation requires an image id")?;
+
+    opts.write_position(writer)?;
+
+    // transmit and display the first frame, then set its gap
+    let header = format!("a=T{},{}", keys, format_keys(&first.image, mode));
+    write_payload(writer, &header, &encode_payload(&first.image, mode, opts)?, opts)?;
+    write_graphics_command(writer, &format!("a=a,i={},q={},r=1,z={}", id, opts.quiet_level(), first.delay_ms), opts.tmux)?;
+
+    for frame in frames {
+        let header = format!(
+            "a=f,i={},q={},z={},{}",
+            id,
+            opts.quiet_level(),
+            frame.delay_ms,
+            format_keys(&frame.image, mode)
+        );
+        write_payload(writer, &header, &encode_payload(&frame.image, mode, opts)?, opts)?;
+    }
+
+    // start the animation, v=1 loops forever, v=2 plays once
+    let loops = if opts.looping { 1 } else { 2 };
+    write_graphics_command(writer, &format!("a=a,i={},q={},s=3,v={}", id, opts.quiet_level(), loops), opts.tmux)?;
+
+    if opts.placeholder {
+        write_placeholders(writer, id, opts.cell_rect(&first.image))?;
+        if opts.is_positioned() {
+            write!(writer, "\x1b8")?;
+        }
+        writer.flush()?;
+        return Ok(());
+    }
+
+    opts.write_end(writer)
+}
+
+impl<'a> PrinterInput<'a> {
+    fn into_reader(self) -> Result<Box<dyn Read + 'a>> {
+        Ok(match self {
+            PrinterInput::File(path) => Box::new(
+                std::fs::File::open(&path).with_context(|| format!("Failed to open {}", path.display()))?,
+            ),
+            PrinterInput::Data(data) => Box::new(Cursor::new(data)),
+            PrinterInput::Reader(reader) => reader,
+        })
+    }
+}
+
+/// Shows text with an external pager command, split shell-style.
+/// Files are passed as the last argument, other input is streamed to the pager's stdin.
+/// If the pager is not installed, the text is written to the writer as is.
+pub fn run_pager(writer: &mut dyn Write, command: &str, input: PrinterInput) -> Result<()> {
+    let mut parts = shell_words::split(command).context("Invalid pager command")?;
+    if parts.is_empty() {
+        anyhow::bail!("Pager command is empty");
+    }
+    let program = parts.remove(0);
+    let mut cmd = std::process::Command::new(&program);
+    cmd.args(parts);
+    match &input {
+        PrinterInput::File(path) => cmd.arg(path),
+        _ => cmd.stdin(std::process::Stdio::piped()),
+    };
+
+    let mut child = match cmd.spawn() {
+        Ok(child) => child,
+        // e.g. minimal containers without the pager
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            std::io::copy(&mut input.into_reader()?, writer)?;
+            writer.flush()?;
+            return Ok(());
+        }
+        Err(e) => return Err(e).with_context(|| format!("Failed to run pager {}", program)),
+    };
+    if let Some(mut stdin) = child.stdin.take() {
+        // a pager quitting early closes its stdin, which is not an error
+        match std::io::copy(&mut input.into_reader()?, &mut stdin) {
+            Err(e) if e.kind() != std::io::ErrorKind::BrokenPipe => return Err(e.into()),
+            _ => {}
+        }
+    }
+    let status = child.wait()?;
+    if !status.success() {
+        anyhow::bail!("Pager {} exited with {}", program, status);
+    }
     Ok(())
 }
 
@@ -111,8 +782,10 @@ pub fn pretty_print(
     input: PrinterInput,
     language: Option<&str>,
     newline: bool,
+    colored: bool,
 ) -> Result<()> {
     let mut printer = PrettyPrinter::new();
+    printer.colored_output(colored);
 
     match input {
         PrinterInput::File(path) => {
@@ -122,6 +795,9 @@ pub fn pretty_print(
             // requires a reader
             printer.input(Input::from_reader(Box::new(Cursor::new(data))));
         }
+        PrinterInput::Reader(reader) => {
+            printer.input(Input::from_reader(reader));
+        }
     };
 
     if let Some(lang) = language {
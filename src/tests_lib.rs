@@ -1,29 +1,122 @@
 // unit tests
+#![allow(clippy::too_many_arguments)]
 use super::*;
 use image::Rgba;
-use image::{DynamicImage, GenericImageView};
 use rstest::rstest;
 use std::path::PathBuf;
 
 const PNG_DATA: &[u8] = include_bytes!("../fixtures/test.png");
 const SVG_DATA: &[u8] = include_bytes!("../fixtures/test.svg");
+const GIF_DATA: &[u8] = include_bytes!("../fixtures/animated.gif");
+const APNG_DATA: &[u8] = include_bytes!("../fixtures/animated.png");
+const WEBP_DATA: &[u8] = include_bytes!("../fixtures/animated.webp");
 
 fn default_ctx() -> KvContext {
-    let resize_fn = |img: &DynamicImage| -> (u32, u32) { img.dimensions() };
     KvContext {
         input_type: InputType::Auto,
-        resize_fn: Box::new(resize_fn),
-        conf_w: None,
-        conf_h: None,
-        term_width: 100,
-        term_height: 50,
-        page_indices: None,
-        use_cache: false,
-        cache_dir: None,
+        resize_mode: ResizeMode::ClipTerminal,
+        resize_filter: FilterType::Triangle,
+        max_width: None,
+        max_height: None,
+        crop: None,
+        rotate: 0,
+        flip_h: false,
+        flip_v: false,
+        brightness: 0,
+        contrast: 0.0,
+        gamma: 1.0,
+        grayscale: false,
+        sepia: false,
+        invert: false,
+        term_size: (100, 50),
+        pages: None,
+        keep_page_order: false,
+        max_pages: DEFAULT_MAX_PAGES,
+        pdf_dpi: None,
+        svg_scale: 1.0,
+        svg_color: None,
+        svg_stylesheet: None,
+        font_dirs: vec![],
+        font_files: vec![],
+        no_system_fonts: false,
+        page_layout: PageLayout::Vertical,
+        viewport: None,
+        html_wait: None,
+        html_delay: None,
+        html_capture_format: CaptureFormat::Png,
+        html_scale: 1.0,
+        chrome_path: None,
+        chrome_args: vec![],
+        no_sandbox: false,
+        page_gap: 0,
+        page_gap_color: Rgba([128, 128, 128, 255]),
+        separate_pages: false,
+        cache_mode: CacheMode::Disabled,
+        render_cache: false,
+        cache_size: 0,
+        video_time: 1.0,
+        progress: false,
+        background: None,
     }
 }
 // get_term_size
-// TODO: implement test
+#[rstest]
+#[case(b"\x1b[4;600;800t", 4, Some((800, 600)))]
+#[case(b"\x1b[6;20;10t", 6, Some((10, 20)))]
+#[case(b"\x1b[6;20;10t", 4, None)]
+#[case(b"\x1b[4;600t", 4, None)]
+#[case(b"\x1b[4;600;800", 4, None)]
+#[case(b"", 4, None)]
+fn test_parse_window_report(#[case] response: &[u8], #[case] code: u32, #[case] expected: Option<(u32, u32)>) {
+    assert_eq!(parse_window_report(response, code), expected);
+}
+
+#[rstest]
+#[case(b"\x1b_Gi=31;OK\x1b\\\x1b[?62;22c", &[], TermCapabilities { kitty: true, sixel: false, iterm2: false })]
+#[case(b"\x1b[?62;4;22c", &[], TermCapabilities { kitty: false, sixel: true, iterm2: false })]
+#[case(b"\x1b[?64;1;42c", &["WezTerm"], TermCapabilities { kitty: false, sixel: false, iterm2: true })]
+#[case(b"", &["tmux", "iTerm2"], TermCapabilities { kitty: false, sixel: false, iterm2: true })]
+fn test_parse_capabilities(#[case] response: &[u8], #[case] programs: &[&str], #[case] expected: TermCapabilities) {
+    let programs: Vec<String> = programs.iter().map(|program| program.to_string()).collect();
+    assert_eq!(parse_capabilities(response, &programs), expected);
+}
+
+#[rstest]
+#[case(Some("xterm-kitty"), true)]
+#[case(Some("xterm-256color"), true)]
+#[case(Some("dumb"), false)]
+#[case(Some("linux"), false)]
+#[case(None, false)]
+fn test_graphics_supported(#[case] term: Option<&str>, #[case] expected: bool) {
+    assert_eq!(graphics_supported(term), expected);
+}
+
+#[rstest]
+#[case(Some("xterm-kitty"), None, false, Some(Mode::Png))]
+#[case(Some("xterm-256color"), None, true, Some(Mode::Png))] // $KITTY_WINDOW_ID
+#[case(Some("xterm-ghostty"), Some("ghostty"), false, Some(Mode::Png))]
+#[case(Some("xterm-256color"), Some("iTerm.app"), false, Some(Mode::Iterm2))]
+#[case(Some("xterm-256color"), Some("WezTerm"), false, Some(Mode::Iterm2))]
+#[case(Some("linux"), None, false, Some(Mode::Blocks))]
+#[case(None, None, false, Some(Mode::Blocks))]
+#[case(Some("xterm-256color"), Some("tmux"), false, None)]
+fn test_mode_from_env(
+    #[case] term: Option<&str>,
+    #[case] term_program: Option<&str>,
+    #[case] kitty_window: bool,
+    #[case] expected: Option<Mode>,
+) {
+    assert_eq!(mode_from_env(term, term_program, kitty_window), expected);
+}
+
+#[rstest]
+#[case(TermCapabilities { kitty: true, sixel: true, iterm2: false }, Mode::Png)]
+#[case(TermCapabilities { kitty: false, sixel: true, iterm2: true }, Mode::Iterm2)]
+#[case(TermCapabilities { kitty: false, sixel: true, iterm2: false }, Mode::Sixel)]
+#[case(TermCapabilities::default(), Mode::Blocks)]
+fn test_mode_from_capabilities(#[case] caps: TermCapabilities, #[case] expected: Mode) {
+    assert_eq!(mode_from_capabilities(caps), expected);
+}
 
 #[rstest]
 #[case("FF0000", Rgba([255, 0, 0, 255]))]
@@ -31,6 +124,19 @@ fn default_ctx() -> KvContext {
 #[case("0000FF", Rgba([0, 0, 255, 255]))]
 #[case("#FFFFFF", Rgba([255, 255, 255, 255]))]
 #[case("#000000", Rgba([0, 0, 0, 255]))]
+#[case("F00", Rgba([255, 0, 0, 255]))]
+#[case("#0af", Rgba([0, 170, 255, 255]))]
+#[case("black", Rgba([0, 0, 0, 255]))]
+#[case("White", Rgba([255, 255, 255, 255]))]
+#[case("CornflowerBlue", Rgba([100, 149, 237, 255]))]
+#[case("transparent", Rgba([0, 0, 0, 0]))]
+#[case("rgb(255,0,0)", Rgba([255, 0, 0, 255]))]
+#[case("rgb( 12 , 34 , 56 )", Rgba([12, 34, 56, 255]))]
+#[case("rgb(300, -5, 128)", Rgba([255, 0, 128, 255]))]
+#[case("RGBA(255, 0, 0, 0.5)", Rgba([255, 0, 0, 128]))]
+#[case("rgba(0,0,255,1)", Rgba([0, 0, 255, 255]))]
+#[case("rgba(0, 0, 255, 0.0)", Rgba([0, 0, 255, 0]))]
+#[case("rgba(0, 0, 255, 2.5)", Rgba([0, 0, 255, 255]))]
 fn test_parse_color(#[case] color: &str, #[case] expected: Rgba<u8>) {
     let result = parse_color(color);
     assert!(result.is_ok());
@@ -41,11 +147,164 @@ fn test_parse_color(#[case] color: &str, #[case] expected: Rgba<u8>) {
 #[case("FF00")]
 #[case("FF000000")]
 #[case("#FF00GG")]
+#[case("€")] // 3 bytes, one character
+#[case("#€€")]
+#[case("notacolor")]
+#[case("rgb(255, 0)")]
+#[case("rgb(255, 0, 0")]
+#[case("rgba(255, 0, 0, 0.5, 1)")]
+#[case("rgb(a, b, c)")]
+#[case("rgba(255, 0, 0, x)")]
 fn test_parse_color_invalid(#[case] color: &str) {
     let result = parse_color(color);
     assert!(result.is_err());
 }
 
+#[rstest]
+#[case("#F00F")]
+#[case("F00F0")]
+fn test_parse_color_invalid_length(#[case] color: &str) {
+    let result = parse_color(color);
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        "Invalid color format: must be 3 or 6 hex characters (e.g. #FFFFFF)"
+    );
+}
+
+#[rstest]
+#[case("#000-#FFF", Rgba([0, 0, 0, 255]), Rgba([255, 255, 255, 255]))]
+#[case("red - blue", Rgba([255, 0, 0, 255]), Rgba([0, 0, 255, 255]))]
+#[case("rgb(0,128,0)-FF0000", Rgba([0, 128, 0, 255]), Rgba([255, 0, 0, 255]))]
+#[case("rgb(-5, 0, 0)-rgb(0, -5, 255)", Rgba([0, 0, 0, 255]), Rgba([0, 0, 255, 255]))] // dashes in colors
+#[case("red-rgb(0,-1,0)", Rgba([255, 0, 0, 255]), Rgba([0, 0, 0, 255]))]
+fn test_parse_gradient(#[case] gradient: &str, #[case] from: Rgba<u8>, #[case] to: Rgba<u8>) {
+    let result = parse_gradient(gradient);
+    assert_eq!(result.unwrap(), (from, to));
+}
+
+#[rstest]
+#[case("#000")]
+#[case("#000-")]
+#[case("nocolor-#FFF")]
+#[case("rgb(-1,0,0)")]
+fn test_parse_gradient_invalid(#[case] gradient: &str) {
+    let result = parse_gradient(gradient);
+    assert!(result.is_err());
+}
+
+#[rstest]
+#[case("#000", "Invalid gradient format: expected FROM-TO (e.g. #000000-#FFFFFF)")]
+#[case("nocolor-#FFF", "Invalid gradient: no dash separates two valid colors")]
+fn test_parse_gradient_error(#[case] gradient: &str, #[case] expected: &str) {
+    let result = parse_gradient(gradient);
+    assert_eq!(result.unwrap_err().to_string(), expected);
+}
+
+#[rstest]
+#[case("vertical", PageLayout::Vertical)]
+#[case("Horizontal", PageLayout::Horizontal)]
+#[case("grid:3", PageLayout::Grid(3))]
+fn test_parse_page_layout(#[case] layout: &str, #[case] expected: PageLayout) {
+    assert_eq!(parse_page_layout(layout).unwrap(), expected);
+}
+
+#[rstest]
+#[case("diagonal")]
+#[case("grid:0")]
+#[case("grid:")]
+fn test_parse_page_layout_invalid(#[case] layout: &str) {
+    assert!(parse_page_layout(layout).is_err());
+}
+
+#[rstest]
+#[case("0,0,100,50", (0, 0, 100, 50))]
+#[case(" 10, 20 ,30,40", (10, 20, 30, 40))]
+fn test_parse_crop(#[case] crop: &str, #[case] expected: (u32, u32, u32, u32)) {
+    assert_eq!(parse_crop(crop).unwrap(), expected);
+}
+
+#[rstest]
+#[case("0,0,100")]
+#[case("0,0,0,50")]
+#[case("0,0,100,50,1")]
+#[case("-1,0,100,50")]
+fn test_parse_crop_invalid(#[case] crop: &str) {
+    assert!(parse_crop(crop).is_err());
+}
+
+#[rstest]
+#[case("1280x720", (1280, 720))]
+#[case("800X600", (800, 600))]
+fn test_parse_viewport(#[case] viewport: &str, #[case] expected: (u32, u32)) {
+    assert_eq!(parse_viewport(viewport).unwrap(), expected);
+}
+
+#[rstest]
+#[case("1280")]
+#[case("0x720")]
+#[case("wxh")]
+fn test_parse_viewport_invalid(#[case] viewport: &str) {
+    assert!(parse_viewport(viewport).is_err());
+}
+
+#[rstest]
+#[case("png", CaptureFormat::Png)]
+#[case("JPEG", CaptureFormat::Jpeg(None))]
+#[case("jpeg:80", CaptureFormat::Jpeg(Some(80)))]
+#[case("webp:0", CaptureFormat::Webp(Some(0)))]
+fn test_parse_capture_format(#[case] format: &str, #[case] expected: CaptureFormat) {
+    assert_eq!(parse_capture_format(format).unwrap(), expected);
+}
+
+#[rstest]
+#[case("gif")]
+#[case("png:80")]
+#[case("jpeg:101")]
+#[case("webp:high")]
+fn test_parse_capture_format_invalid(#[case] format: &str) {
+    assert!(parse_capture_format(format).is_err());
+}
+
+#[rstest]
+#[case(Fit::Contain, (100, 50), (40, 20))]
+#[case(Fit::Contain, (50, 100), (20, 40))]
+#[case(Fit::Cover, (100, 50), (80, 40))] // overflow is cropped later
+#[case(Fit::Stretch, (100, 50), (40, 40))]
+#[case(Fit::Letterbox, (100, 50), (40, 20))] // padding is added later
+fn test_calculate_dimensions_fit(#[case] fit: Fit, #[case] img_dims: (u32, u32), #[case] expected: (u32, u32)) {
+    let mode = ResizeMode::Manual { width: Some(40), height: Some(40), fit };
+    assert_eq!(calculate_dimensions(img_dims, mode, (10, 10)), expected);
+}
+
+#[rstest]
+#[case((4000, 2000), Some(1000), None, (1000, 500))]
+#[case((4000, 2000), None, Some(1000), (2000, 1000))]
+#[case((4000, 2000), Some(1000), Some(200), (400, 200))] // the tighter limit wins
+#[case((800, 400), Some(1000), Some(1000), (800, 400))] // never upscaled
+fn test_clamp_dimensions(
+    #[case] dims: (u32, u32),
+    #[case] max_width: Option<u32>,
+    #[case] max_height: Option<u32>,
+    #[case] expected: (u32, u32),
+) {
+    assert_eq!(clamp_dimensions(dims, max_width, max_height), expected);
+}
+
+#[rstest]
+#[case(1000, 500, 40, (40, 20))]
+#[case(300, 600, 40, (20, 40))] // longest side is the height
+#[case(10, 5, 40, (10, 5))] // never upscaled
+fn test_calculate_dimensions_thumbnail(
+    #[case] img_w: u32,
+    #[case] img_h: u32,
+    #[case] max_side: u32,
+    #[case] expected: (u32, u32),
+) {
+    // the terminal size is ignored
+    let dims = calculate_dimensions((img_w, img_h), ResizeMode::Thumbnail(max_side), (10, 10));
+    assert_eq!(dims, expected);
+}
+
 #[rstest]
 #[case(100, 50, Some(50), None, false, false, false, false, 50, 25)] // explicit width
 #[case(100, 50, None, Some(25), false, false, false, false, 50, 25)] // explicit height
@@ -80,15 +339,25 @@ fn test_calculate_dimensions(
             && (fullwidth || fullheight || resize || noresize))
     );
 
-    let (w, h) = calculate_dimensions(
-        img_dims,
-        (conf_w, conf_h),
-        fullwidth,
-        fullheight,
-        resize,
-        noresize,
-        (term_width, term_height),
-    );
+    let mode = if noresize {
+        ResizeMode::Original
+    } else if resize {
+        ResizeMode::FitTerminal
+    } else if fullwidth {
+        ResizeMode::FitWidth
+    } else if fullheight {
+        ResizeMode::FitHeight
+    } else if conf_w.is_some() || conf_h.is_some() {
+        ResizeMode::Manual {
+            width: conf_w,
+            height: conf_h,
+            fit: Fit::Stretch,
+        }
+    } else {
+        ResizeMode::ClipTerminal
+    };
+
+    let (w, h) = calculate_dimensions(img_dims, mode, (term_width, term_height));
 
     assert_eq!(w, expected_w);
     assert_eq!(h, expected_h);
@@ -103,15 +372,40 @@ fn test_calculate_dimensions(
 #[case("1-3", vec![0, 1, 2])]
 #[case("1-3,5", vec![0, 1, 2, 4])]
 #[case("1-3,5-7", vec![0, 1, 2, 4, 5, 6])]
-fn test_parse_pages(#[case] input: &str, #[case] expected: Vec<u16>) {
+#[case("8-", vec![7, 8, 9])]
+#[case("-3", vec![0, 1, 2])]
+#[case("-1", vec![0])]
+#[case("10-", vec![9])]
+#[case("5-1", vec![0, 1, 2, 3, 4])]
+#[case(" 9 - ,1", vec![0, 8, 9])]
+#[case("2-10:3", vec![1, 4, 7])]
+#[case("1-10:2", vec![0, 2, 4, 6, 8])]
+#[case("-5:2", vec![0, 2, 4])]
+#[case("10,all", vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9])]
+fn test_parse_pages(#[case] input: &str, #[case] expected: Vec<u32>) {
     let result = parse_pages(input);
     assert!(result.is_ok());
-    assert_eq!(result.unwrap(), Some(expected));
+    // resolved against a document with 10 pages
+    assert_eq!(resolve_pages(&result.unwrap().unwrap(), 10, false, DEFAULT_MAX_PAGES).unwrap(), expected);
 }
 
-#[test]
-fn test_parse_pages_empty() {
-    let result = parse_pages("");
+#[rstest]
+#[case("3,1,2", vec![2, 0, 1])]
+#[case("1,1,2", vec![0, 0, 1])]
+#[case("5-1", vec![4, 3, 2, 1, 0])]
+#[case("3-2,1-4", vec![2, 1, 0, 1, 2, 3])]
+#[case("10-1:4", vec![9, 5, 1])]
+#[case("10,all", vec![9, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9])]
+fn test_resolve_pages_keep_order(#[case] input: &str, #[case] expected: Vec<u32>) {
+    let ranges = parse_pages(input).unwrap().unwrap();
+    assert_eq!(resolve_pages(&ranges, 10, true, DEFAULT_MAX_PAGES).unwrap(), expected);
+}
+
+#[rstest]
+#[case("")]
+#[case("all")]
+fn test_parse_pages_all(#[case] input: &str) {
+    let result = parse_pages(input);
     assert!(result.is_ok());
     assert_eq!(result.unwrap(), None);
 }
@@ -119,13 +413,53 @@ fn test_parse_pages_empty() {
 #[rstest]
 #[case("a")]
 #[case("0")]
-#[case("-1")]
-#[case("1-2,4-3")]
+#[case("-0")]
+#[case("-")]
+#[case("1-2,0-3")]
+#[case("1-a")]
+#[case("1-10:0")]
+#[case("1-10:-2")]
+#[case("1-10:x")]
+#[case("5:2")]
 fn test_parse_pages_invalid(#[case] input: &str) {
     let result = parse_pages(input);
     assert!(result.is_err());
 }
 
+#[test]
+fn test_parse_pages_step_error() {
+    let err = parse_pages("1-10:0").unwrap_err();
+    assert_eq!(err.to_string(), "Page step must be >= 1");
+}
+
+#[rstest]
+#[case("70000", 100_000, vec![69_999])] // above u16
+#[case("99990-", 100_000, (99_989..100_000).collect())]
+fn test_resolve_pages_large(#[case] input: &str, #[case] n_pages: u32, #[case] expected: Vec<u32>) {
+    let ranges = parse_pages(input).unwrap().unwrap();
+    assert_eq!(resolve_pages(&ranges, n_pages, false, DEFAULT_MAX_PAGES).unwrap(), expected);
+}
+
+#[rstest]
+#[case("1-4000000000", 1000)]
+#[case("1,all", 1000)]
+#[case("1-10,all", 1005)] // duplicates count as well
+fn test_resolve_pages_too_many(#[case] input: &str, #[case] max_pages: u32) {
+    // rejected before allocating billions of pages
+    let ranges = parse_pages(input).unwrap().unwrap();
+    let err = resolve_pages(&ranges, u32::MAX, false, max_pages).unwrap_err();
+    assert_eq!(err.to_string(), format!("Page selection has more than {} pages", max_pages));
+}
+
+#[rstest]
+#[case("11", "Page 11 out of range (must be between 1 and 10)")]
+#[case("5-12", "Page 12 out of range (must be between 1 and 10)")]
+#[case("12-", "Page 12 out of range (must be between 1 and 10)")]
+fn test_resolve_pages_out_of_range(#[case] input: &str, #[case] err_msg: &str) {
+    let ranges = parse_pages(input).unwrap().unwrap();
+    assert_eq!(resolve_pages(&ranges, 10, false, DEFAULT_MAX_PAGES).unwrap_err().to_string(), err_msg);
+}
+
 #[rstest]
 #[case(PathBuf::from("fixtures/test.svg"), InputType::Svg)]
 #[case(PathBuf::from("fixtures/test.png"), InputType::Image)]
@@ -140,7 +474,11 @@ fn test_load_file(#[case] path: PathBuf, #[case] input_type: InputType) {
 }
 
 #[rstest]
-#[case(PathBuf::from("nonexistent"), InputType::Auto, "Failed to open file")]
+#[case(
+    PathBuf::from("nonexistent"),
+    InputType::Auto,
+    "Failed to open file: nonexistent"
+)]
 #[case(
     PathBuf::from("fixtures/test.random"),
     InputType::Auto,
@@ -195,6 +533,179 @@ fn test_load_data(#[case] data: &[u8]) {
     assert!(result.is_ok());
 }
 
+#[rstest]
+#[case("fixtures/test.png", "image", Some((1, 1)), Some((10, 10)))]
+#[case("fixtures/test.jpg", "image", Some((1, 1)), Some((10, 10)))]
+#[case("fixtures/animated.gif", "animation", Some((4, 4)), Some((10, 10)))]
+#[case("fixtures/animated.webp", "animation", Some((2, 2)), Some((10, 10)))]
+#[case("fixtures/test.svg", "svg", Some((1, 1)), Some((10, 10)))]
+#[case("fixtures/zero_size.svg", "svg", Some((20, 10)), Some((10, 5)))] // viewBox size
+#[case("fixtures/test.html", "html", None, None)]
+#[case("README.md", "text", None, None)]
+fn test_inspect_file(
+    #[case] path: &str,
+    #[case] kind: &str,
+    #[case] size: Option<(u32, u32)>,
+    #[case] target: Option<(u32, u32)>,
+) {
+    let mut ctx = default_ctx();
+    ctx.resize_mode = ResizeMode::Manual { width: Some(10), height: None, fit: Fit::Contain };
+    let info = inspect_file(&ctx, Path::new(path)).unwrap();
+    assert_eq!(info.kind, kind);
+    assert_eq!(info.width.zip(info.height), size);
+    assert_eq!(info.target_width.zip(info.target_height), target);
+}
+
+#[test]
+fn test_walk_dir() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(dir.path().join("b/c")).unwrap();
+    for name in ["b/c/x.PNG", "b/doc.pdf", "a.svg", "notes.txt", "b/c/no_extension"] {
+        std::fs::write(dir.path().join(name), b"").unwrap();
+    }
+    let files = walk_dir(dir.path()).unwrap();
+    let expected: Vec<PathBuf> = ["a.svg", "b/c/x.PNG", "b/doc.pdf"].iter().map(|name| dir.path().join(name)).collect();
+    assert_eq!(files, expected);
+}
+
+#[rstest]
+#[case(ResizeMode::Original, None, 0, (40, 20))]
+#[case(ResizeMode::Original, Some((0, 0, 10, 8)), 0, (10, 8))]
+#[case(ResizeMode::Original, None, 90, (20, 40))]
+#[case(ResizeMode::Manual { width: Some(10), height: Some(10), fit: Fit::Cover }, None, 0, (10, 10))]
+#[case(ResizeMode::Manual { width: Some(10), height: Some(10), fit: Fit::Letterbox }, None, 0, (10, 10))]
+#[case(ResizeMode::Manual { width: Some(10), height: Some(10), fit: Fit::Contain }, None, 0, (10, 5))]
+fn test_target_dimensions(
+    #[case] resize_mode: ResizeMode,
+    #[case] crop: Option<(u32, u32, u32, u32)>,
+    #[case] rotate: u32,
+    #[case] expected: (u32, u32),
+) {
+    let mut ctx = default_ctx();
+    ctx.resize_mode = resize_mode;
+    ctx.crop = crop;
+    ctx.rotate = rotate;
+    assert_eq!(target_dimensions(&ctx, (40, 20)), expected);
+}
+
+#[rstest]
+#[case(b"line one\nline two\n", InputType::Auto, true)]
+#[case(b"a\nb \xC3", InputType::Auto, true)] // cut inside a character
+#[case(b"fixtures/test.png\n", InputType::Auto, false)] // might be a path
+#[case(b"a\nb\xFF\n", InputType::Auto, false)]
+#[case(b"<svg>\n</svg>", InputType::Auto, false)]
+#[case(b"%PDF-1.7\n%\n", InputType::Auto, false)]
+#[case(PNG_DATA, InputType::Auto, false)]
+#[case(PNG_DATA, InputType::Text, true)]
+#[case(b"line one\nline two\n", InputType::Image, false)]
+fn test_is_text_prefix(#[case] prefix: &[u8], #[case] input_type: InputType, #[case] expected: bool) {
+    let mut ctx = default_ctx();
+    ctx.input_type = input_type;
+    assert_eq!(is_text_prefix(&ctx, prefix), expected);
+}
+
+#[rstest]
+#[case(None, None, false, true)]
+#[case(Some(Background::Checkerboard(8)), None, false, false)]
+#[case(None, Some(4), false, false)] // resized
+#[case(None, None, true, false)] // flipped
+fn test_load_data_png(
+    #[case] background: Option<Background>,
+    #[case] width: Option<u32>,
+    #[case] flip_h: bool,
+    #[case] unmodified: bool,
+) {
+    let mut ctx = default_ctx();
+    ctx.background = background;
+    ctx.flip_h = flip_h;
+    if width.is_some() {
+        ctx.resize_mode = ResizeMode::Manual { width, height: None, fit: Fit::Stretch };
+    }
+    let result = load_data(&ctx, PNG_DATA, "").unwrap();
+    match result {
+        LoadResult::Png { data, .. } => {
+            assert!(unmodified);
+            assert_eq!(data, PNG_DATA);
+        }
+        LoadResult::Image(_) => assert!(!unmodified),
+        _ => panic!("unexpected load result"),
+    }
+}
+
+#[rstest]
+#[case(b"\x00\x00\x00\x18ftypheic\x00\x00\x00\x00", "HEIC images are not supported")]
+#[case(b"\x00\x00\x00\x18ftypavif\x00\x00\x00\x00", "AVIF images are not supported")]
+#[case(b"\x00\x00\x00\x18ftypmif1\x00\x00\x00\x00mif1avif", "AVIF images are not supported")] // generic brand
+#[case(b"\x00\x00\x00\x18ftypmif1\x00\x00\x00\x00mif1heic", "HEIC images are not supported")]
+#[case(b"\xFF\x0A\xFA\x7F", "JPEG XL images are not supported")]
+#[case(b"\x00\x00\x01\x00\x01\x00", "Failed to decode ICO image")]
+fn test_load_data_unsupported(#[case] data: &[u8], #[case] expected_error: &str) {
+    let result = load_data(&default_ctx(), data, "png");
+    assert_eq!(result.unwrap_err().to_string(), expected_error);
+}
+
+#[test]
+fn test_load_data_ico() {
+    let mut ico = Vec::new();
+    DynamicImage::new_rgba8(2, 2)
+        .write_to(&mut Cursor::new(&mut ico), image::ImageFormat::Ico)
+        .unwrap();
+    // the wrong extension is ignored
+    let result = load_data(&default_ctx(), &ico, "svg").unwrap();
+    let LoadResult::Image(img) = result else {
+        panic!("Expected an image");
+    };
+    assert_eq!((img.width(), img.height()), (2, 2));
+}
+
+#[cfg(feature = "http")]
+#[rstest]
+#[case("https://example.com/cat.PNG", "png")]
+#[case("https://example.com/docs/file.pdf?download=1#page=2", "pdf")]
+#[case("https://example.com/", "")]
+#[case("https://example.com", "")]
+#[case("https://example.com/dir.d/file", "")]
+fn test_url_extension(#[case] url: &str, #[case] expected: &str) {
+    assert_eq!(url_extension(url), expected);
+}
+
+#[cfg(feature = "video")]
+#[rstest]
+#[case(b"\x00\x00\x00\x20ftypisom\x00\x00\x02\x00", "", true)]
+#[case(b"\x1A\x45\xDF\xA3\x01", "", true)]
+#[case(b"", "webm", true)]
+#[case(b"\x00\x00\x00\x18ftypavif\x00\x00\x00\x00", "", false)]
+#[case(PNG_DATA, "png", false)]
+fn test_is_video(#[case] data: &[u8], #[case] extension: &str, #[case] expected: bool) {
+    assert_eq!(is_video(data, extension), expected);
+}
+
+#[rstest]
+fn test_load_data_animation(
+    #[values(GIF_DATA, WEBP_DATA, APNG_DATA)] data: &[u8],
+    #[values(InputType::Auto, InputType::Image)] input_type: InputType,
+) {
+    let mut ctx = default_ctx();
+    ctx.input_type = input_type;
+    let result = load_data(&ctx, data, "");
+    let Ok(LoadResult::Animation(frames)) = result else {
+        panic!("Expected an animation");
+    };
+    let delays: Vec<u32> = frames.iter().map(|f| f.delay_ms).collect();
+    assert_eq!(delays, vec![100, 200, 300]);
+    assert_eq!(frames[1].image.to_rgba8().get_pixel(0, 0), &Rgba([0, 255, 0, 255]));
+}
+
+#[test]
+fn test_load_data_static_webp() {
+    let mut webp = Vec::new();
+    DynamicImage::new_rgba8(2, 2)
+        .write_to(&mut Cursor::new(&mut webp), image::ImageFormat::WebP)
+        .unwrap();
+    let result = load_data(&default_ctx(), &webp, "");
+    assert!(matches!(result, Ok(LoadResult::Image(_))));
+}
+
 #[rstest]
 #[case("fixtures/test.svg".as_bytes())]
 #[case(SVG_DATA)]
@@ -205,15 +716,19 @@ fn test_load_data_svg(#[case] data: &[u8]) {
 }
 
 #[rstest]
-#[case("nonexistent".as_bytes(), Some("Failed to decode input: The image format could not be determined"))]
+#[case("nonexistent".as_bytes())]
+#[case(b"")]
+fn test_load_data_text(#[case] data: &[u8]) {
+    let ctx = default_ctx();
+    let result = load_data(&ctx, data, "");
+    assert!(matches!(result, Ok(LoadResult::Data(d)) if d == data));
+}
+
+#[rstest]
 #[case(
     b"invalidbinary\x99\x98\x97\x96",
     Some("Failed to decode input: The image format could not be determined")
 )]
-#[case(
-    b"",
-    Some("Failed to decode input: The image format could not be determined")
-)]
 fn test_load_data_invalid(#[case] data: &[u8], #[case] err_msg: Option<&str>) {
     let ctx = default_ctx();
     let result = load_data(&ctx, data, "");
@@ -229,3 +744,113 @@ fn test_load_data_invalid_svg(#[case] data: &[u8], #[case] err_msg: Option<&str>
     assert!(result.is_err());
     assert_eq!(result.unwrap_err().to_string(), err_msg.unwrap());
 }
+
+#[test]
+fn test_encode_image() {
+    let img = DynamicImage::new_rgba8(2, 1);
+    let opts = SendOptions {
+        mode: Mode::Raw,
+        ..Default::default()
+    };
+    let data = encode_image(&img, &opts).unwrap();
+    // 8 transparent bytes
+    assert_eq!(data, b"\x1b_Ga=T,f=32,s=2,v=1,m=0;AAAAAAAAAAA=\x1b\\\n");
+}
+
+#[rstest]
+#[case("out.png", b"\x89PNG")]
+#[case("out.JPG", b"\xff\xd8\xff")]
+#[case("out.jpeg", b"\xff\xd8\xff")]
+#[case("out.webp", b"RIFF")]
+#[case("out.bmp", b"BM")]
+#[case("out.unknown", b"\x89PNG")]
+#[case("-", b"\x89PNG")]
+fn test_encode_image_output_format(#[case] output: &str, #[case] magic: &[u8]) {
+    let img = DynamicImage::new_rgba8(2, 2);
+    let opts = SendOptions {
+        output: Some(output.to_string()),
+        ..Default::default()
+    };
+    let data = encode_image(&img, &opts).unwrap();
+    assert!(data.starts_with(magic));
+}
+
+#[test]
+fn test_encode_image_jpeg_quality() {
+    let img = DynamicImage::ImageRgba8(image::RgbaImage::from_fn(32, 32, |x, y| Rgba([(x * 8) as u8, (y * 8) as u8, 0, 255])));
+    let size = |quality| {
+        let opts = SendOptions {
+            output: Some("out.jpg".to_string()),
+            quality,
+            ..Default::default()
+        };
+        encode_image(&img, &opts).unwrap().len()
+    };
+    assert!(size(10) < size(100));
+}
+
+#[rstest]
+#[case(Rgba([255, 0, 0, 255]), Rgba([0, 0, 255, 255]), "\x1b[38;2;255;0;0;48;2;0;0;255m▀")]
+#[case(Rgba([255, 0, 0, 255]), Rgba([0, 0, 0, 0]), "\x1b[38;2;255;0;0;49m▀")]
+#[case(Rgba([0, 0, 0, 0]), Rgba([0, 0, 255, 255]), "\x1b[38;2;0;0;255;49m▄")]
+#[case(Rgba([0, 0, 0, 0]), Rgba([0, 0, 0, 0]), "\x1b[0m ")]
+fn test_encode_image_blocks(#[case] top: Rgba<u8>, #[case] bottom: Rgba<u8>, #[case] expected_cell: &str) {
+    // one cell of two pixels, repeated in a second row
+    let img = image::RgbaImage::from_fn(2, 4, |_, y| if y % 2 == 0 { top } else { bottom });
+    let opts = SendOptions {
+        mode: Mode::Blocks,
+        cell_size: (1, 2),
+        ..Default::default()
+    };
+    let data = String::from_utf8(encode_image(&DynamicImage::ImageRgba8(img), &opts).unwrap()).unwrap();
+    let row = format!("{}{}\x1b[0m", expected_cell, expected_cell);
+    assert_eq!(data, format!("{}\n{}\n", row, row));
+}
+
+#[test]
+fn test_quantize_dither() {
+    // horizontal gray gradient reduced to a 4-color palette
+    let gradient = image::RgbaImage::from_fn(64, 8, |x, _| {
+        let v = (x * 4) as u8;
+        Rgba([v, v, v, 255])
+    });
+    let quant = color_quant::NeuQuant::new(10, 4, gradient.as_raw());
+    let palette = quant.color_map_rgb();
+
+    // error of the mean gray of each 8x8 block compared to the original
+    let block_error = |indices: &[Option<usize>]| -> f32 {
+        (0..8)
+            .map(|block| {
+                let (mut original, mut quantized) = (0.0, 0.0);
+                for y in 0..8 {
+                    for x in block * 8..block * 8 + 8 {
+                        original += gradient.get_pixel(x, y)[0] as f32;
+                        quantized += palette[indices[(y * 64 + x) as usize].unwrap() * 3] as f32;
+                    }
+                }
+                ((original - quantized) / 64.0).abs()
+            })
+            .sum()
+    };
+
+    let plain = quantize(&gradient, &quant, false);
+    let dithered = quantize(&gradient, &quant, true);
+    assert_ne!(plain, dithered);
+    // error diffusion keeps the local average closer to the gradient
+    assert!(block_error(&dithered) < block_error(&plain) / 2.0);
+}
+
+#[test]
+fn test_context_builder() {
+    let ctx = KvContext::new()
+        .with_input_type(InputType::Svg)
+        .with_term_size(100, 50)
+        .with_cache_mode(CacheMode::Disabled);
+    assert_eq!(ctx.input_type, InputType::Svg);
+    assert_eq!(ctx.pages, Some(vec![PageRange::from(0)]));
+    // 1x1 SVG, not resized
+    match load_data(&ctx, SVG_DATA, "").unwrap() {
+        LoadResult::Image(img) => assert_eq!((img.width(), img.height()), (1, 1)),
+        _ => panic!("expected an image"),
+    }
+}
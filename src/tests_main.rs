@@ -1,5 +1,7 @@
 // implementation tests
+#![allow(clippy::too_many_arguments)]
 use super::*;
+use base64::{engine::general_purpose, Engine as _};
 use rstest::rstest;
 use std::io::Cursor;
 
@@ -12,24 +14,108 @@ fn default_conf() -> Config {
         files: vec![],
         width: None,
         height: None,
+        fit: FitOption::Stretch,
+        keep_aspect: false,
+        cols: None,
+        rows: None,
+        cell_size: (10, 20),
         fullwidth: false,
         fullheight: false,
         resize: false,
         noresize: false,
+        term_width: None,
+        term_height: None,
+        reserve_rows: DEFAULT_RESERVE_ROWS,
+        thumbnail: None,
+        max_width: None,
+        max_height: None,
+        filter: FilterOption::Triangle,
+        crop: None,
+        rotate: None,
+        flip_h: false,
+        flip_v: false,
+        brightness: 0,
+        contrast: 0.0,
+        gamma: 1.0,
+        grayscale: false,
+        sepia: false,
+        invert: false,
         background: false,
         color: "#FFFFFF".to_string(),
+        checkerboard: None,
+        gradient: None,
+        gradient_horizontal: false,
         mode: ModeOption::Png,
+        placeholder: false,
+        image_id: None,
+        col: None,
+        row: None,
+        in_place: false,
+        z_index: None,
+        chunk_size: KITTY_CHUNK_SIZE,
+        tmux: false,
+        quiet: None,
+        transfer: TransferOption::Direct,
+        dither: false,
         output: None,
+        quality: 90,
+        output_dir: None,
+        name_template: "{stem}.png".to_string(),
+        stdout_png: false,
+        detect_mode: false,
+        no_color: false,
         overwrite: false,
         input: InputTypeOption::Auto,
         pages: "1".to_string(),
+        keep_page_order: false,
+        max_pages: DEFAULT_MAX_PAGES,
+        dpi: None,
+        svg_scale: 1.0,
+        svg_color: None,
+        svg_stylesheet: None,
+        font_dir: vec![],
+        font_file: vec![],
+        no_system_fonts: false,
+        page_layout: "vertical".to_string(),
+        page_gap: 10,
+        page_gap_color: "#808080".to_string(),
+        separate_pages: false,
+        montage: None,
+        thumb_size: None,
+        montage_gap: 10,
+        viewport: None,
+        html_wait: None,
+        html_delay: None,
+        html_capture_format: "png".to_string(),
+        html_scale: 1.0,
+        chrome_path: None,
+        chrome_arg: vec![],
+        no_sandbox: false,
+        video_time: 1.0,
         all: false,
         language: None,
+        pager: None,
+        no_loop: false,
         no_newline: false,
         no_cache: false,
+        cache: false,
+        cache_size: 100,
+        jobs: None,
         printname: true, // default to true for tests
         tty: false,
         remove: false,
+        remove_placements: false,
+        remove_cursor: false,
+        delete_id: None,
+        plugins: false,
+        probe: false,
+        info: false,
+        recursive: false,
+        watch: false,
+        generate_man: false,
+        progress: false,
+        config: None,
+        no_config: false,
     }
 }
 
@@ -121,6 +207,375 @@ fn test_resize(
     );
 }
 
+// --cols, --rows
+#[rstest]
+#[case(Some(500_000_000), None)]
+#[case(None, Some(u32::MAX))]
+fn test_resize_cells_overflow(#[case] cols: Option<u32>, #[case] rows: Option<u32>) {
+    let mut conf = default_conf();
+    conf.files = vec!["fixtures/test.png".into()];
+    conf.cols = cols;
+    conf.rows = rows;
+    let expected_error = "Error: --cols or --rows is too large for the cell size\n";
+    run_test(conf, false, Cursor::new(&[]), "", expected_error, 1, false, (800, 400), None);
+}
+
+#[rstest]
+#[case(Some(4), None, (10, 20), (40, 20))]
+#[case(None, Some(2), (10, 20), (80, 40))]
+#[case(Some(4), Some(1), (10, 20), (40, 20))] // stretched to the cells
+#[case(Some(3), None, (7, 15), (21, 11))]
+fn test_resize_cells(
+    #[case] cols: Option<u32>,
+    #[case] rows: Option<u32>,
+    #[case] cell_size: (u32, u32),
+    #[case] expected: (u32, u32),
+) {
+    let svg_data = "<svg width='100' height='50' xmlns='http://www.w3.org/2000/svg'><rect width='100' height='50' fill='red'/></svg>";
+    let mut conf = default_conf();
+    conf.mode = ModeOption::Raw; // to get width/height in output
+    conf.cols = cols;
+    conf.rows = rows;
+    conf.cell_size = cell_size;
+    let expected_output = format!("\x1b_Ga=T,f=32,s={},v={}", expected.0, expected.1);
+    run_test(
+        conf,
+        true,
+        Cursor::new(svg_data.as_bytes()),
+        &expected_output,
+        "stdin\n",
+        0,
+        true,
+        (800, 400),
+        None,
+    );
+}
+
+// --mode
+#[rstest]
+#[case(ModeOption::Png, "\x1b_Ga=T,f=100,m=0;")]
+#[case(ModeOption::Raw, "\x1b_Ga=T,f=32,s=10,v=6,m=0;")]
+#[case(ModeOption::Zlib, "\x1b_Ga=T,f=32,s=10,v=6,o=z,m=0;")]
+#[case(ModeOption::Sixel, "\x1bP0;1;q\"1;1;10;6#0;2;")]
+#[case(ModeOption::Iterm2, "\x1b]1337;File=inline=1;size=")]
+#[case(ModeOption::Iterm2, ";width=10px;height=6px;preserveAspectRatio=1:iVBORw0KGgo")]
+#[case(ModeOption::Blocks, "\x1b[38;2;255;0;0;48;2;255;0;0m▀\x1b[0m")]
+fn test_mode(#[case] mode: ModeOption, #[case] expected_output: &str) {
+    let svg_data = "<svg width='10' height='6' xmlns='http://www.w3.org/2000/svg'><rect width='10' height='6' fill='red'/></svg>";
+    let mut conf = default_conf();
+    conf.mode = mode;
+    run_test(
+        conf,
+        true,
+        Cursor::new(svg_data.as_bytes()),
+        expected_output,
+        "stdin\n",
+        0,
+        true,
+        (800, 400),
+        None,
+    );
+}
+
+#[test]
+fn test_mode_sixel_data() {
+    let svg_data = "<svg width='10' height='6' xmlns='http://www.w3.org/2000/svg'><rect width='10' height='6' fill='red'/></svg>";
+    let mut conf = default_conf();
+    conf.mode = ModeOption::Sixel;
+    let mut output = Vec::new();
+    let code = run(
+        &mut output,
+        &mut Vec::new(),
+        Cursor::new(svg_data.as_bytes()),
+        conf,
+        (800, 400),
+        true,
+        None,
+    )
+    .unwrap();
+    assert_eq!(code, 0);
+    let output_str = String::from_utf8(output).unwrap();
+    // a single band of one color, all six rows set ('~'), run-length encoded
+    assert!(output_str.ends_with("!10~-\x1b\\\n"), "{:?}", output_str);
+    assert!(output_str.contains(";2;100;0;0#"), "{:?}", output_str);
+}
+
+// --image-id, --placeholder
+#[rstest]
+#[case(false, "\x1b_Ga=T,i=42,q=2,f=100,m=0;")]
+#[case(true, "\x1b_Ga=T,i=42,q=2,U=1,c=")]
+#[case(true, "\x1b\\\x1b[38;2;0;0;42m\u{10EEEE}\u{305}\u{305}")]
+fn test_image_id(#[case] placeholder: bool, #[case] expected_output: &str) {
+    let mut conf = default_conf();
+    conf.files = vec!["fixtures/test.png".into()];
+    conf.image_id = Some(42);
+    conf.placeholder = placeholder;
+    run_test(
+        conf,
+        false,
+        Cursor::new(&[]),
+        expected_output,
+        "fixtures/test.png\n",
+        0,
+        true,
+        (800, 400),
+        None,
+    );
+}
+
+#[test]
+fn test_placeholder_cells() {
+    let svg_data = "<svg width='30' height='50' xmlns='http://www.w3.org/2000/svg'><rect width='30' height='50' fill='red'/></svg>";
+    let mut conf = default_conf();
+    conf.image_id = Some(0x01020304);
+    conf.placeholder = true;
+    run_test(
+        conf,
+        true,
+        Cursor::new(svg_data.as_bytes()),
+        // second row, second column, most significant byte of the id
+        "\x1b[38;2;2;3;4m\u{10EEEE}\u{30D}\u{305}\u{30D}\u{10EEEE}\u{30D}\u{30D}\u{30D}",
+        "stdin\n",
+        0,
+        true,
+        (800, 400),
+        None,
+    );
+}
+
+// --col, --row, --in-place
+#[rstest]
+#[case(Some(3), Some(5), false, "\x1b7\x1b[6;4H\x1b_Ga=T,f=")]
+#[case(Some(3), None, false, "\x1b7\x1b[4G\x1b_Ga=T,f=")]
+#[case(None, Some(5), false, "\x1b7\x1b[6d\x1b_Ga=T,f=")]
+#[case(None, None, false, "\x1b_Ga=T,f=")]
+#[case(None, None, true, "\x1b7\x1b_Ga=T,C=1,f=")]
+#[case(Some(3), None, true, "\x1b7\x1b[4G\x1b_Ga=T,C=1,f=")]
+fn test_position(
+    #[case] col: Option<u32>,
+    #[case] row: Option<u32>,
+    #[case] in_place: bool,
+    #[case] expected_start: &str,
+) {
+    let mut conf = default_conf();
+    conf.files = vec!["fixtures/test.png".into()];
+    conf.col = col;
+    conf.row = row;
+    conf.in_place = in_place;
+    let mut output = Vec::new();
+    let code = run(
+        &mut output,
+        &mut Vec::new(),
+        Cursor::new(&[]),
+        conf,
+        (800, 400),
+        false,
+        None,
+    )
+    .unwrap();
+    assert_eq!(code, 0);
+    let output_str = String::from_utf8(output).unwrap();
+    assert!(output_str.starts_with(expected_start), "{:?}", output_str);
+    let expected_end = if col.is_some() || row.is_some() || in_place { "\x1b\\\x1b8" } else { "\x1b\\\n" };
+    assert!(output_str.ends_with(expected_end), "{:?}", output_str);
+}
+
+// --z
+#[rstest]
+#[case(ModeOption::Png, Some(-1), "\x1b_Ga=T,z=-1,f=100,m=0;")]
+#[case(ModeOption::Raw, Some(5), "\x1b_Ga=T,z=5,f=32,")]
+#[case(ModeOption::Png, None, "\x1b_Ga=T,f=100,m=0;")]
+#[case(ModeOption::Sixel, Some(5), "\x1bP0;1;q")]
+fn test_z_index(
+    #[case] mode: ModeOption,
+    #[case] z_index: Option<i32>,
+    #[case] expected_output: &str,
+) {
+    let mut conf = default_conf();
+    conf.files = vec!["fixtures/test.png".into()];
+    conf.mode = mode;
+    conf.z_index = z_index;
+    run_test(
+        conf,
+        false,
+        Cursor::new(&[]),
+        expected_output,
+        "fixtures/test.png\n",
+        0,
+        true,
+        (800, 400),
+        None,
+    );
+}
+
+// --montage, --thumb-size
+#[rstest]
+#[case(1, None, "s=1,v=12")] // 1x1 images with 10 pixels of padding
+#[case(2, None, "s=12,v=1")]
+#[case(2, Some(4), "s=18,v=4")]
+fn test_montage(
+    #[case] cols: u32,
+    #[case] thumb_size: Option<u32>,
+    #[case] expected_size: &str,
+    #[values(None, Some(1))] jobs: Option<u32>, // one batch or one file at a time
+) {
+    let mut conf = default_conf();
+    conf.jobs = jobs;
+    conf.files = vec!["fixtures/test.png".into(), "fixtures/test.svg".into()];
+    conf.mode = ModeOption::Raw; // to get width/height in output
+    conf.montage = Some(cols);
+    conf.thumb_size = thumb_size;
+    run_test(
+        conf,
+        false,
+        Cursor::new(&[]),
+        &format!("\x1b_Ga=T,f=32,{},m=", expected_size),
+        "fixtures/test.png\nfixtures/test.svg\n",
+        0,
+        true,
+        (800, 400),
+        None,
+    );
+}
+
+// --chunk-size
+#[rstest]
+#[case(4)]
+#[case(64)]
+#[case(KITTY_CHUNK_SIZE)]
+fn test_chunk_size(#[case] chunk_size: usize) {
+    let mut conf = default_conf();
+    conf.files = vec!["fixtures/test.png".into()];
+    conf.mode = ModeOption::Raw;
+    conf.chunk_size = chunk_size;
+    let mut output = Vec::new();
+    let code = run(
+        &mut output,
+        &mut Vec::new(),
+        Cursor::new(&[]),
+        conf,
+        (800, 400),
+        false,
+        None,
+    )
+    .unwrap();
+    assert_eq!(code, 0);
+    let output_str = String::from_utf8(output).unwrap();
+    let chunks: Vec<&str> = output_str
+        .split("\x1b_G")
+        .skip(1)
+        .map(|chunk| chunk.split_once(';').unwrap().1.trim_end_matches("\x1b\\\n").trim_end_matches("\x1b\\"))
+        .collect();
+    let (last, rest) = chunks.split_last().unwrap();
+    assert!(rest.iter().all(|chunk| chunk.len() == chunk_size));
+    assert!(!last.is_empty() && last.len() <= chunk_size);
+}
+
+// --quiet
+#[rstest]
+#[case(None, None, "\x1b_Ga=T,f=100,m=0;")]
+#[case(Some(2), None, "\x1b_Ga=T,q=2,f=100,m=0;")]
+#[case(None, Some(7), "\x1b_Ga=T,i=7,q=2,f=100,m=0;")]
+#[case(Some(1), Some(7), "\x1b_Ga=T,i=7,q=1,f=100,m=0;")]
+fn test_quiet(
+    #[case] quiet: Option<u8>,
+    #[case] image_id: Option<u32>,
+    #[case] expected_output: &str,
+) {
+    let mut conf = default_conf();
+    conf.files = vec!["fixtures/test.png".into()];
+    conf.quiet = quiet;
+    conf.image_id = image_id;
+    run_test(
+        conf,
+        false,
+        Cursor::new(&[]),
+        expected_output,
+        "fixtures/test.png\n",
+        0,
+        true,
+        (800, 400),
+        None,
+    );
+}
+
+// --tmux
+#[rstest]
+#[case(false, "\x1b_Ga=T,f=100,m=0;")]
+#[case(true, "\x1bPtmux;\x1b\x1b_Ga=T,f=100,m=0;")]
+fn test_tmux(#[case] tmux: bool, #[case] expected_output: &str) {
+    let mut conf = default_conf();
+    conf.files = vec!["fixtures/test.png".into()];
+    conf.tmux = tmux;
+    let expected_end = if tmux { "\x1b\x1b\\\x1b\\\n" } else { "\x1b\\\n" };
+    let mut output = Vec::new();
+    let code = run(
+        &mut output,
+        &mut Vec::new(),
+        Cursor::new(&[]),
+        conf,
+        (800, 400),
+        false,
+        None,
+    )
+    .unwrap();
+    assert_eq!(code, 0);
+    let output_str = String::from_utf8(output).unwrap();
+    assert!(output_str.starts_with(expected_output), "{:?}", output_str);
+    assert!(output_str.ends_with(expected_end), "{:?}", output_str);
+}
+
+// --transfer
+#[test]
+fn test_transfer_file() {
+    let mut conf = default_conf();
+    conf.files = vec!["fixtures/test.png".into()];
+    conf.transfer = TransferOption::File;
+    let mut output = Vec::new();
+    let code = run(
+        &mut output,
+        &mut Vec::new(),
+        Cursor::new(&[]),
+        conf,
+        (800, 400),
+        false,
+        None,
+    )
+    .unwrap();
+    assert_eq!(code, 0);
+    let output_str = String::from_utf8(output).unwrap();
+    let encoded = output_str
+        .strip_prefix("\x1b_Ga=T,f=100,t=t;")
+        .and_then(|s| s.strip_suffix("\x1b\\\n"))
+        .unwrap_or_else(|| panic!("{:?}", output_str));
+    let path = String::from_utf8(general_purpose::STANDARD.decode(encoded).unwrap()).unwrap();
+    assert!(path.contains("tty-graphics-protocol"));
+    let data = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(data, std::fs::read("fixtures/test.png").unwrap());
+}
+
+// --stdout-png
+#[test]
+fn test_stdout_png() {
+    let mut conf = default_conf();
+    conf.files = vec!["fixtures/test.png".into()];
+    conf.stdout_png = true;
+    let mut output = Vec::new();
+    let code = run(
+        &mut output,
+        &mut Vec::new(),
+        Cursor::new(&[]),
+        conf,
+        (800, 400),
+        false,
+        None,
+    )
+    .unwrap();
+    assert_eq!(code, 0);
+    assert_eq!(output, std::fs::read("fixtures/test.png").unwrap());
+}
+
 // --background, --color
 // TODO: implement
 
@@ -152,15 +607,17 @@ fn test_output() {
     assert_eq!(code, 0);
     let error_output_str = String::from_utf8(error_output).unwrap();
     assert_eq!(error_output_str, "fixtures/test.png\n");
-    assert!(output.starts_with(b"\x89PNG"));
+    // unmodified PNGs are written as is
+    assert_eq!(output, std::fs::read("fixtures/test.png").unwrap());
 }
 
 #[rstest]
 #[case(vec![],"0", false, "Error: Invalid page range\n")]
-#[case(vec![],"-1", false, "Error: Invalid page range\n")]
-#[case(vec!["fixtures/test.pdf".into()],"2", false, "fixtures/test.pdf\nError loading fixtures/test.pdf: Page index out of range (must be <= 1)\n")]
-#[case(vec!["fixtures/test.pdf".into(),"fixtures/test.png".into()],"1", false, "Error: Cannot specify multiple files with --pages\n")]
+#[case(vec![],"-0", false, "Error: Invalid page range\n")]
+#[case(vec!["fixtures/test.pdf".into()],"2", false, "fixtures/test.pdf\nError loading fixtures/test.pdf: Page 2 out of range (must be between 1 and 1)\n")]
+#[case(vec!["fixtures/test.pdf".into(),"fixtures/test.png".into()],"2", false, "Error: Cannot specify multiple files with non-default --pages option\n")]
 #[case(vec!["fixtures/test.pdf".into()],"1", true, "fixtures/test.pdf\n")]
+#[case(vec!["fixtures/pages.pdf".into()],"2,99", false, "fixtures/pages.pdf\nError loading fixtures/pages.pdf: Page 99 out of range (must be between 1 and 3)\n")]
 fn test_pages(
     #[case] files: Vec<PathBuf>,
     #[case] pages: &str,
@@ -197,6 +654,30 @@ fn test_pages(
     }
 }
 
+#[test]
+fn test_separate_pages() {
+    let mut conf = default_conf();
+    conf.files = vec!["fixtures/test.pdf".into()];
+    conf.separate_pages = true;
+    conf.all = true;
+    let mut output = Vec::new();
+    let code = run(
+        &mut output,
+        &mut Vec::new(),
+        Cursor::new(&[]),
+        conf,
+        (800, 400),
+        false,
+        None,
+    )
+    .unwrap();
+    assert_eq!(code, 0);
+    let output_str = String::from_utf8(output).unwrap();
+    // the fixture has a single page
+    assert_eq!(output_str.matches("\x1b_Ga=T").count(), 1);
+    assert!(output_str.ends_with('\n'));
+}
+
 // --tty
 #[rstest]
 #[case(vec![], false, NO_FILES_MSG)]
@@ -237,6 +718,39 @@ fn test_force_tty(
     }
 }
 
+// --jobs
+#[test]
+fn test_jobs() {
+    let run_jobs = |jobs| {
+        let mut conf = default_conf();
+        conf.files = vec![
+            "fixtures/test.png".into(),
+            "fixtures/missing.png".into(),
+            "fixtures/test.svg".into(),
+        ];
+        conf.jobs = Some(jobs);
+        let mut output = Vec::new();
+        let mut error_output = Vec::new();
+        let code = run(
+            &mut output,
+            &mut error_output,
+            Cursor::new(&[]),
+            conf,
+            (800, 400),
+            false,
+            None,
+        )
+        .unwrap();
+        (code, output, String::from_utf8(error_output).unwrap())
+    };
+    let (code, output, error_str) = run_jobs(3);
+    assert_eq!(code, 1);
+    assert!(error_str.starts_with("fixtures/test.png\nfixtures/missing.png\nError loading fixtures/missing.png"));
+    assert!(error_str.ends_with("\nfixtures/test.svg\n"));
+    // same order as sequential loading
+    assert_eq!((code, output, error_str), run_jobs(1));
+}
+
 // --printname
 #[rstest]
 fn test_printname(#[values(false, true)] printname: bool) {
@@ -282,6 +796,78 @@ fn test_remove(
     );
 }
 
+// --remove-placements, --remove-cursor
+#[rstest]
+#[case(true, false, "\x1b_Ga=d,d=a\x1b\\")]
+#[case(false, true, "\x1b_Ga=d,d=c\x1b\\")]
+fn test_remove_mode(
+    #[case] remove_placements: bool,
+    #[case] remove_cursor: bool,
+    #[case] expected_output: &str,
+) {
+    let mut conf = default_conf();
+    conf.remove_placements = remove_placements;
+    conf.remove_cursor = remove_cursor;
+    conf.files = vec!["fixtures/test.png".into()];
+
+    run_test(
+        conf,
+        false,
+        Cursor::new(&[]),
+        expected_output,
+        "",
+        0,
+        false,
+        (800, 400),
+        None,
+    );
+}
+
+// --delete-id
+#[rstest]
+fn test_delete_id(
+    #[values(false, true)] is_input_available: bool,
+    #[values(vec![], vec!["fixtures/test.png".into()])] files: Vec<PathBuf>,
+) {
+    let mut conf = default_conf();
+    conf.delete_id = Some(42);
+    conf.files = files;
+
+    run_test(
+        conf,
+        is_input_available,
+        Cursor::new(&[]),
+        "\x1b_Ga=d,d=i,i=42\x1b\\",
+        "",
+        0,
+        false,
+        (800, 400),
+        None,
+    );
+}
+
+#[rstest]
+#[case(true, None)]
+#[case(false, Some(42))]
+fn test_delete_id_conflicts(#[case] remove: bool, #[case] image_id: Option<u32>) {
+    let mut conf = default_conf();
+    conf.delete_id = Some(42);
+    conf.remove = remove;
+    conf.image_id = image_id;
+
+    run_test(
+        conf,
+        false,
+        Cursor::new(&[]),
+        "",
+        "Error: Cannot combine --delete-id with --remove or --image-id\n",
+        1,
+        false,
+        (800, 400),
+        None,
+    );
+}
+
 // [FILES]
 #[rstest]
 #[case(vec![])]
@@ -305,6 +891,38 @@ fn test_stdin(
     );
 }
 
+// --no-loop
+#[rstest]
+#[case(false, "s=3,v=1")]
+#[case(true, "s=3,v=2")]
+fn test_animation(#[case] no_loop: bool, #[case] expected_output: &str) {
+    let mut conf = default_conf();
+    conf.files = vec!["fixtures/animated.gif".into()];
+    conf.no_loop = no_loop;
+    run_test(
+        conf.clone(),
+        false,
+        Cursor::new(&[]),
+        ",z=200,f=100,m=0;",
+        "fixtures/animated.gif\n",
+        0,
+        true,
+        (800, 400),
+        None,
+    );
+    run_test(
+        conf,
+        false,
+        Cursor::new(&[]),
+        expected_output,
+        "fixtures/animated.gif\n",
+        0,
+        true,
+        (800, 400),
+        None,
+    );
+}
+
 #[test]
 fn test_no_input() {
     let conf = default_conf();
@@ -324,7 +942,7 @@ fn test_no_input() {
 #[rstest]
 #[case(vec!["fixtures/test.png".into()], "fixtures/test.png\n", 0)]
 #[case(vec!["fixtures/test.jpg".into(), "fixtures/test.png".into()], "fixtures/test.jpg\nfixtures/test.png\n", 0)]
-#[case(vec!["fixtures/test.png".into(), "nonexistent".into()], "fixtures/test.png\nnonexistent\nError loading nonexistent: Failed to open file\n", 1)]
+#[case(vec!["fixtures/test.png".into(), "nonexistent".into()], "fixtures/test.png\nnonexistent\nError loading nonexistent: Failed to open file: nonexistent\n", 1)]
 fn test_files(
     #[case] files: Vec<PathBuf>,
     #[case] expected_error: &str,
@@ -344,3 +962,321 @@ fn test_files(
         None,
     );
 }
+
+// --term-width, --term-height
+#[test]
+fn test_resolve_term_size() {
+    let conf = Config::try_parse_from(["kv", "--term-width", "640", "--term-height", "480"]).unwrap();
+    assert_eq!(resolve_term_size(&conf), (640, 480));
+    assert!(Config::try_parse_from(["kv", "--term-width", "0"]).is_err());
+}
+
+// --generate-man
+#[test]
+fn test_write_man_page() {
+    let mut output = Vec::new();
+    write_man_page(&mut output).unwrap();
+    let man = String::from_utf8(output).unwrap();
+    assert!(man.contains(".TH kv 1"), "{}", man);
+    // the help strings of the flags are reused
+    assert!(man.contains("Print a man page in roff format to stdout and exit"));
+}
+
+// config.toml
+#[rstest]
+#[case(&["kv"], ModeOption::Sixel, 50, Some(4))]
+#[case(&["kv", "-m", "zlib", "--quality", "70"], ModeOption::Zlib, 70, Some(4))] // flags win
+#[case(&["kv", "-o", "out.png"], ModeOption::Auto, 50, Some(4))] // the mode conflicts with -o
+#[case(&["kv", "--gradient", "red-blue"], ModeOption::Sixel, 50, None)] // replaces the background
+fn test_file_config(
+    #[case] args: &[&str],
+    #[case] mode: ModeOption,
+    #[case] quality: u8,
+    #[case] checkerboard: Option<u32>,
+) {
+    let file: FileConfig = toml::from_str("mode = \"sixel\"\nquality = 50\ncheckerboard = 4").unwrap();
+    let matches = Config::command().try_get_matches_from(args).unwrap();
+    let mut conf = Config::from_arg_matches(&matches).unwrap();
+    file.apply(&mut conf, &matches).unwrap();
+    assert_eq!(conf.mode, mode);
+    assert_eq!(conf.quality, quality);
+    assert_eq!(conf.checkerboard, checkerboard);
+}
+
+#[rstest]
+#[case(&["kv", "-b"], true, "red")] // background from the flag, color from the file
+#[case(&["kv", "-b", "-c", "blue"], true, "blue")]
+#[case(&["kv", "--checkerboard"], false, "red")]
+fn test_file_config_color(#[case] args: &[&str], #[case] background: bool, #[case] color: &str) {
+    let file: FileConfig = toml::from_str("color = \"red\"").unwrap();
+    let matches = Config::command().try_get_matches_from(args).unwrap();
+    let mut conf = Config::from_arg_matches(&matches).unwrap();
+    file.apply(&mut conf, &matches).unwrap();
+    assert_eq!(conf.background, background);
+    assert_eq!(conf.color, color);
+}
+
+#[rstest]
+#[case("quality = 0", "quality must be between 1 and 100")]
+#[case("background = true\ncheckerboard = 8", "background and checkerboard cannot be combined")]
+fn test_file_config_invalid(#[case] content: &str, #[case] expected: &str) {
+    let file: FileConfig = toml::from_str(content).unwrap();
+    let matches = Config::command().try_get_matches_from(["kv"]).unwrap();
+    let mut conf = Config::from_arg_matches(&matches).unwrap();
+    assert_eq!(file.apply(&mut conf, &matches).unwrap_err().to_string(), expected);
+}
+
+#[test]
+fn test_parse_config() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.toml");
+    std::fs::write(&path, "filter = \"catmull-rom\"\nreserve-rows = 5\n").unwrap();
+    let path = path.to_str().unwrap();
+
+    let matches = Config::command().try_get_matches_from(["kv", "--config", path]).unwrap();
+    let conf = parse_config(&matches).unwrap();
+    assert_eq!((conf.filter, conf.reserve_rows), (FilterOption::CatmullRom, 5));
+
+    let matches = Config::command().try_get_matches_from(["kv", "--no-config"]).unwrap();
+    let conf = parse_config(&matches).unwrap();
+    assert_eq!((conf.filter, conf.reserve_rows), (FilterOption::Triangle, DEFAULT_RESERVE_ROWS));
+
+    // unknown keys are typos
+    std::fs::write(path, "filtr = \"nearest\"\n").unwrap();
+    let matches = Config::command().try_get_matches_from(["kv", "--config", path]).unwrap();
+    assert!(parse_config(&matches).is_err());
+    let matches = Config::command().try_get_matches_from(["kv", "--config", "missing.toml"]).unwrap();
+    assert!(parse_config(&matches).is_err());
+}
+
+// --probe
+#[test]
+fn test_probe() {
+    let mut conf = default_conf();
+    conf.probe = true;
+    conf.mode = ModeOption::Sixel;
+    let mut output = Vec::new();
+    let mut error = Vec::new();
+    let code = run(&mut output, &mut error, Cursor::new(&[]), conf, (800, 400), false, None).unwrap();
+    assert_eq!(code, 0);
+    assert!(output.is_empty());
+    let error_str = String::from_utf8(error).unwrap();
+    // the capabilities depend on the terminal running the tests
+    assert!(error_str.starts_with("terminal size: 800x400 px\ncell size: "), "{:?}", error_str);
+    assert!(error_str.contains("\nkitty graphics: "), "{:?}", error_str);
+    assert!(error_str.ends_with("\nmode: sixel\n"), "{:?}", error_str);
+}
+
+// --output-dir, --name-template
+#[test]
+fn test_output_dir() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut conf = default_conf();
+    conf.files = vec!["fixtures/test.png".into(), "fixtures/test.svg".into()];
+    conf.output_dir = Some(dir.path().to_path_buf());
+    conf.name_template = "{index}-{stem}.png".to_string();
+    conf.printname = false;
+    run_test(conf.clone(), false, Cursor::new(&[]), "", "", 0, false, (800, 400), None);
+
+    // unmodified PNGs are written as is, everything else is encoded
+    let png = std::fs::read(dir.path().join("1-test.png")).unwrap();
+    assert_eq!(png, std::fs::read("fixtures/test.png").unwrap());
+    let svg = image::open(dir.path().join("2-test.png")).unwrap();
+    assert!(svg.width() > 0);
+
+    // existing files are kept
+    run_test(
+        conf,
+        false,
+        Cursor::new(&[]),
+        "",
+        &format!(
+            "Error writing fixtures/test.png: Output file already exists: {} (use --overwrite)\n\
+             Error writing fixtures/test.svg: Output file already exists: {} (use --overwrite)\n",
+            dir.path().join("1-test.png").display(),
+            dir.path().join("2-test.png").display()
+        ),
+        1,
+        false,
+        (800, 400),
+        None,
+    );
+}
+
+#[rstest]
+#[case("{stem}.png", "doc.png")]
+#[case("{index:}-{stem}-{page}.png", "{index:}-doc-2.png")]
+#[case("{index}/{page}", "3/2")]
+fn test_output_name(#[case] template: &str, #[case] expected: &str) {
+    assert_eq!(output_name(template, "doc", 3, 2), expected);
+}
+
+// --output -
+#[test]
+fn test_prepare_writer_stdout() {
+    let (_, temp_output) = prepare_writer(Some("-".to_string()), false).unwrap();
+    // no temporary file, so nothing named "-" is persisted
+    assert!(temp_output.is_none());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_persist_output_permissions() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("out.png");
+    std::fs::write(&path, b"old").unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o640)).unwrap();
+
+    let (mut writer, temp_output) = prepare_writer(Some(path.to_string_lossy().into_owned()), true).unwrap();
+    writer.write_all(b"new").unwrap();
+    writer.flush().unwrap();
+    drop(writer);
+    let (tempfile, final_path) = temp_output.unwrap();
+    persist_output(tempfile, &final_path).unwrap();
+
+    assert_eq!(std::fs::read(&path).unwrap(), b"new");
+    assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o640);
+}
+
+// --pager
+#[rstest]
+#[case(true)]
+#[case(false)]
+fn test_pager(#[case] use_stdin: bool) {
+    let dir = tempfile::tempdir().unwrap();
+    let out = dir.path().join("out.txt");
+    let mut conf = default_conf();
+    conf.printname = false;
+    // writes stdin, or the file given as last argument, to out.txt
+    conf.pager = Some(format!("sh -c 'cat \"$@\" > {}' sh", out.display()));
+    conf.input = InputTypeOption::Text;
+    if !use_stdin {
+        conf.files = vec!["fixtures/test.svg".into()];
+    }
+    let input = std::fs::read("fixtures/test.svg").unwrap();
+    run_test(conf, use_stdin, Cursor::new(input.as_slice()), "", "", 0, false, (800, 400), None);
+    assert_eq!(std::fs::read(&out).unwrap(), input);
+}
+
+#[cfg(feature = "glob")]
+#[rstest]
+#[case(&["fixtures/test.[pj]*g"], Ok(vec!["fixtures/test.jpg", "fixtures/test.png"]))]
+#[case(&["fixtures/test.svg", "README.md"], Ok(vec!["fixtures/test.svg", "README.md"]))]
+#[case(&["https://example.org/?q=*"], Ok(vec!["https://example.org/?q=*"]))]
+#[case(&["fixtures/*.missing"], Err("No files match fixtures/*.missing"))]
+fn test_expand_globs(#[case] files: &[&str], #[case] expected: Result<Vec<&str>, &str>) {
+    let files: Vec<PathBuf> = files.iter().map(PathBuf::from).collect();
+    match (expand_globs(&files), expected) {
+        (Ok(expanded), Ok(expected)) => assert_eq!(expanded, expected.iter().map(PathBuf::from).collect::<Vec<_>>()),
+        (Err(e), Err(expected)) => assert_eq!(e.to_string(), expected),
+        (result, _) => panic!("unexpected result {:?}", result),
+    }
+}
+
+#[rstest]
+#[case(false, &["dir", "fixtures/test.svg"])]
+#[case(true, &["dir/a/c.png", "dir/b.png", "fixtures/test.svg"])]
+fn test_resolve_files(#[case] recursive: bool, #[case] expected: &[&str]) {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("a")).unwrap();
+    std::fs::write(dir.path().join("b.png"), b"").unwrap();
+    std::fs::write(dir.path().join("a/c.png"), b"").unwrap();
+    let files = resolve_files(&[dir.path().into(), "fixtures/test.svg".into()], recursive).unwrap();
+    let expected: Vec<PathBuf> = expected
+        .iter()
+        .map(|path| match path.strip_prefix("dir") {
+            Some(rest) => dir.path().join(rest.trim_start_matches('/')),
+            None => path.into(),
+        })
+        .collect();
+    assert_eq!(files, expected);
+}
+
+#[rstest]
+#[case(notify::EventKind::Modify(notify::event::ModifyKind::Any), "/a/b.svg", true)]
+#[case(notify::EventKind::Create(notify::event::CreateKind::File), "/a/b.svg", true)]
+#[case(notify::EventKind::Modify(notify::event::ModifyKind::Any), "/a/c.svg", false)] // other file
+#[case(notify::EventKind::Access(notify::event::AccessKind::Any), "/a/b.svg", false)]
+#[case(notify::EventKind::Remove(notify::event::RemoveKind::File), "/a/b.svg", false)]
+fn test_is_change(#[case] kind: notify::EventKind, #[case] path: &str, #[case] expected: bool) {
+    let event = notify::Event::new(kind).add_path(path.into());
+    assert_eq!(is_change(&event, &[PathBuf::from("/a/b.svg")]), expected);
+}
+
+#[test]
+fn test_directory_without_recursive() {
+    let mut conf = default_conf();
+    conf.printname = false;
+    conf.files = vec!["fixtures".into()];
+    let expected_error = "Error loading fixtures: Is a directory, use --recursive to show the files in it\n";
+    run_test(conf, false, Cursor::new(&[]), "", expected_error, 1, false, (800, 400), None);
+}
+
+#[test]
+fn test_recursive() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("a")).unwrap();
+    std::fs::copy("fixtures/test.png", dir.path().join("b.png")).unwrap();
+    std::fs::copy("fixtures/test.png", dir.path().join("a/c.png")).unwrap();
+    std::fs::write(dir.path().join("a/notes.txt"), "skipped").unwrap();
+    let mut conf = default_conf();
+    conf.info = true;
+    conf.recursive = true;
+    conf.files = vec![dir.path().into(), "fixtures/test.svg".into()];
+    let mut output = Vec::new();
+    let code = run(&mut output, Vec::new(), Cursor::new(&[][..]), conf, (800, 400), false, None).unwrap();
+    assert_eq!(code, 0);
+    // one JSON line per file, sorted within the directory, in argument order otherwise
+    let names: Vec<String> = String::from_utf8(output)
+        .unwrap()
+        .lines()
+        .map(|line| line.split('"').nth(3).unwrap().to_string())
+        .collect();
+    let expected = [dir.path().join("a/c.png"), dir.path().join("b.png"), "fixtures/test.svg".into()];
+    assert_eq!(names, expected.map(|path| path.display().to_string()));
+}
+
+#[rstest]
+#[case(false, "{\"name\":\"fixtures/test.png\",\"type\":\"image\",\"format\":\"PNG\",\"width\":1,\"height\":1,\"target_width\":20,\"target_height\":20,\"pages\":null,\"mode\":\"png\"}\n")]
+#[case(true, "{\"name\":\"stdin\",\"type\":\"image\",\"format\":\"PNG\",\"width\":1,\"height\":1,\"target_width\":20,\"target_height\":20,\"pages\":null,\"mode\":\"png\"}\n")]
+fn test_info(#[case] use_stdin: bool, #[case] expected: &str) {
+    let mut conf = default_conf();
+    conf.info = true;
+    conf.width = Some(20);
+    if !use_stdin {
+        conf.files = vec!["fixtures/test.png".into()];
+    }
+    let input = std::fs::read("fixtures/test.png").unwrap();
+    // nothing is rendered or printed besides the JSON
+    run_test(conf, use_stdin, Cursor::new(input.as_slice()), expected, "", 0, false, (800, 400), None);
+}
+
+#[rstest]
+#[case(ModeOption::Sixel, true)] // explicit modes are never detected
+#[case(ModeOption::Auto, false)] // main resolved it to png
+fn test_detect_mode_skipped(#[case] mode: ModeOption, #[case] detect: bool) {
+    let mut conf = default_conf();
+    conf.mode = mode.clone();
+    conf.detect_mode = detect;
+    detect_mode(&mut conf);
+    assert_eq!(conf.mode, mode);
+    assert!(!conf.detect_mode);
+}
+
+#[rstest]
+#[case(true)]
+#[case(false)]
+fn test_pager_missing(#[case] use_stdin: bool) {
+    let mut conf = default_conf();
+    conf.printname = false;
+    conf.pager = Some("kv-missing-pager --flag".to_string());
+    conf.input = InputTypeOption::Text;
+    if !use_stdin {
+        conf.files = vec!["fixtures/test.svg".into()];
+    }
+    // written as is instead of failing
+    let input = std::fs::read_to_string("fixtures/test.svg").unwrap();
+    run_test(conf, use_stdin, Cursor::new(input.as_bytes()), &input, "", 0, false, (800, 400), None);
+}